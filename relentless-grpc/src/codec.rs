@@ -2,6 +2,7 @@ use std::marker::PhantomData;
 
 use bytes::Buf;
 use prost_reflect::{prost::Message, DescriptorPool, DynamicMessage, MessageDescriptor, MethodDescriptor};
+use relentless::record::metric::BodySizeRecorder;
 use serde::{Deserializer, Serialize, Serializer};
 use tonic::{
     codec::{Codec, Decoder, Encoder},
@@ -10,20 +11,27 @@ use tonic::{
 
 use crate::contract::MethodPath;
 
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug)]
 pub struct DynamicCodec<D, S> {
     method: MethodDescriptor,
     serializer: S,
+    size: BodySizeRecorder,
     phantom: PhantomData<(D, S)>,
 }
 impl<D, S: Clone> Clone for DynamicCodec<D, S> {
     fn clone(&self) -> Self {
-        Self { method: self.method.clone(), serializer: self.serializer.clone(), phantom: PhantomData }
+        let (method, serializer, size) = (self.method.clone(), self.serializer.clone(), self.size.clone());
+        Self { method, serializer, size, phantom: PhantomData }
     }
 }
 impl<D, S> DynamicCodec<D, S> {
     pub fn new(method: MethodDescriptor, serializer: S) -> Self {
-        Self { method, serializer, phantom: PhantomData }
+        Self { method, serializer, size: Default::default(), phantom: PhantomData }
+    }
+    /// Sizes of the last encoded request and decoded response message without gRPC frame headers,
+    /// recorded by the encoder and decoder of this codec and its clones.
+    pub fn size(&self) -> BodySizeRecorder {
+        self.size.clone()
     }
     pub fn with_pool(pool: DescriptorPool, method_path: &MethodPath, serializer: S) -> Option<Self> {
         Some(Self::new(Self::get_method(pool, method_path)?, serializer))
@@ -50,16 +58,16 @@ where
     type Decoder = DynamicDecoder<S>;
 
     fn encoder(&mut self) -> Self::Encoder {
-        DynamicEncoder(self.method.input(), PhantomData)
+        DynamicEncoder(self.method.input(), self.size.clone(), PhantomData)
     }
 
     fn decoder(&mut self) -> Self::Decoder {
-        DynamicDecoder(self.method.output(), self.serializer.clone())
+        DynamicDecoder(self.method.output(), self.serializer.clone(), self.size.clone())
     }
 }
 
 #[derive(Debug)]
-pub struct DynamicEncoder<D>(MessageDescriptor, PhantomData<D>);
+pub struct DynamicEncoder<D>(MessageDescriptor, BodySizeRecorder, PhantomData<D>);
 impl<D> Encoder for DynamicEncoder<D>
 where
     D: for<'x> Deserializer<'x>,
@@ -69,17 +77,17 @@ where
     type Error = Status;
 
     fn encode(&mut self, item: Self::Item, dst: &mut tonic::codec::EncodeBuf<'_>) -> Result<(), Self::Error> {
-        let Self(descriptor, _phantom) = self;
-        DynamicMessage::deserialize(descriptor.clone(), item)
-            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?
-            .encode(dst)
+        let Self(descriptor, size, _phantom) = self;
+        let message = DynamicMessage::deserialize(descriptor.clone(), item)
             .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+        size.record_request(message.encoded_len() as u64);
+        message.encode(dst).map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
         Ok(())
     }
 }
 
 #[derive(Debug)]
-pub struct DynamicDecoder<S>(MessageDescriptor, S);
+pub struct DynamicDecoder<S>(MessageDescriptor, S, BodySizeRecorder);
 impl<S> Decoder for DynamicDecoder<S>
 where
     S: Serializer + Clone + Send + 'static,
@@ -90,10 +98,9 @@ where
     type Error = Status;
 
     fn decode(&mut self, src: &mut tonic::codec::DecodeBuf<'_>) -> Result<Option<Self::Item>, Self::Error> {
-        if !src.has_remaining() {
-            return Ok(None);
-        }
-        let Self(descriptor, serializer) = self;
+        // empty buffer is a valid message with default values, such as `Int64Value { value: 0 }`
+        let Self(descriptor, serializer, size) = self;
+        size.record_response(src.remaining() as u64);
         let dynamic_message = DynamicMessage::decode(descriptor.clone(), src) // TODO `decode` requires ownership of MethodDescriptor
             .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
         Ok(Some(
@@ -16,9 +16,12 @@ use http::uri::PathAndQuery;
 use prost::Message;
 use prost_reflect::DescriptorPool;
 use prost_types::FileDescriptorProto;
-use relentless::shot::{
-    contract::{Contract, SignContract},
-    job::BasePath,
+use relentless::{
+    record::metric::BodySizeRecorder,
+    shot::{
+        contract::{Contract, SignContract},
+        job::BasePath,
+    },
 };
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use tonic::{client::GrpcService, transport::Body, Status};
@@ -266,6 +269,10 @@ where
     type ResSink = GrpcResponse;
 
     type SignError = Infallible;
+
+    fn body_size(response: &mut Self::Response) -> BodySizeRecorder {
+        response.extensions().get::<BodySizeRecorder>().cloned().unwrap_or_default()
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -294,11 +301,13 @@ where
     fn call(&mut self, req: (MethodPath, tonic::Request<D>)) -> Self::Future {
         let (method_path, request) = req;
         let codec = DynamicCodec::with_pool(self.pool.clone(), &method_path, JsonSerializer::default()).unwrap();
-        let mut grpc = tonic::client::Grpc::new(self.service.clone());
+        let (mut grpc, size) = (tonic::client::Grpc::new(self.service.clone()), codec.size());
         Box::pin(async move {
             let path = method_path.format().map_err(|e| Status::unknown(e.to_string()))?;
             grpc.ready().await.map_err(|e| Status::unknown(format!("Service was not ready: {}", e.into())))?; // ref https://github.com/hyperium/tonic/blob/v0.14.2/tonic-build/src/client.rs#L240-L242
-            grpc.unary(request, path, codec).await
+            let mut response = grpc.unary(request, path, codec).await?;
+            response.extensions_mut().insert(size);
+            Ok(response)
         })
     }
 }
@@ -309,12 +318,12 @@ pub struct MethodPath {
     method: String,
 }
 impl FromStr for MethodPath {
-    type Err = Infallible;
+    type Err = relentless::Error;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         match &s.split('/').collect::<Vec<_>>()[..] {
             &[service, method] | &["", service, method] => Ok((service, method).into()),
-            _ => todo!(),
+            _ => Err(relentless::Error::custom(format!("`{s}` is not a method path like `package.Service/Method`"))),
         }
     }
 }
@@ -384,10 +393,10 @@ mod tests {
         assert_eq!(method_path2.parts(), ("greeter.Greeter", "SayHello"));
         assert_eq!(method_path2.format().unwrap(), PathAndQuery::from_static("/greeter.Greeter/SayHello"));
 
-        // let error = MethodPath::from_str("//greeter.Greeter/SayHello").unwrap_err();
-        // assert_eq!(
-        //     error.to_string(),
-        //     todo!()
-        // );
+        let error = MethodPath::from_str("//greeter.Greeter/SayHello").unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "`//greeter.Greeter/SayHello` is not a method path like `package.Service/Method`"
+        );
     }
 }
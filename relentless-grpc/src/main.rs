@@ -12,12 +12,10 @@ pub async fn main() -> Result<ExitCode, Box<dyn std::error::Error>> {
     };
 
     Cli::run(|job: Job<_, _, _>, spec| async move {
-        // let measure = MeasureLayer::new();
         let otel = OtelInterceptor;
         let make = MakeChannel(otel);
         let report = job.shot::<_, _, DynamicContract<serde_json::Value, JsonSerializer>>(make, &spec).await?;
         spec.report(&report)?;
-        // dbg!(measure.aggregated().times());
         Ok((!report.evaluated.assess().success() as u8).into())
     })
     .await
@@ -1,4 +1,4 @@
-use std::{convert::Infallible, str::FromStr};
+use std::str::FromStr;
 
 use relentless::{shot::contract::RequestSource, template::Template};
 use semigroup::Semigroup;
@@ -44,18 +44,30 @@ impl RequestSource<(MethodPath, tonic::Request<serde_json::Value>)> for GrpcRequ
         template: &Template,
     ) -> Result<(MethodPath, tonic::Request<serde_json::Value>), Self::Error> {
         let target = template.render(target)?;
-        let pq = MethodPath::from_str(&target).map_err(relentless::Error::boxed)?;
+        let pq = MethodPath::from_str(&target)?;
         let request =
             self.message.as_ref().unwrap_or(&Default::default()).produce(destination, &target, template).await?;
         Ok((pq, tonic::Request::from_parts(Default::default(), Default::default(), request)))
     }
+
+    async fn dry_run(&self, destination: &http::Uri, target: &str, template: &Template) -> Result<String, Self::Error> {
+        let (method_path, request) = self.produce(destination, target, template).await?;
+        let path = method_path.format().map_err(relentless::Error::boxed)?;
+        let message = serde_json::to_string_pretty(request.get_ref()).map_err(relentless::Error::boxed)?;
+        Ok(format!("{path}\n{message}\n"))
+    }
 }
 impl RequestSource<serde_json::Value> for GrpcRequestMessage {
-    type Error = Infallible;
-    async fn produce(&self, _: &http::Uri, _: &str, _template: &Template) -> Result<serde_json::Value, Self::Error> {
+    type Error = relentless::Error;
+    async fn produce(&self, _: &http::Uri, _: &str, template: &Template) -> Result<serde_json::Value, Self::Error> {
         match self {
             Self::Empty => Ok(serde_json::json!({})),
-            Self::Value(v) => Ok(v.clone()),
+            Self::Value(v) => template.render_json_recursive(v),
         }
     }
+
+    async fn dry_run(&self, destination: &http::Uri, target: &str, template: &Template) -> Result<String, Self::Error> {
+        let message = self.produce(destination, target, template).await?;
+        serde_json::to_string(&message).map_err(relentless::Error::boxed)
+    }
 }
@@ -3,16 +3,18 @@ use std::fmt::Debug;
 use relentless::{
     error::EvaluateError,
     evaluator::{
-        evaluate::{Evaluator, Failure, Messages},
-        expect::ExpectEvaluator,
+        evaluate::{Evaluator, Failure, Messages, Quorum},
+        json::{self, JsonEvaluator, NoiseEvaluator},
+        query::QueryEvaluator,
+        schema::SchemaEvaluator,
     },
-    shot::{contract::ResponseSink, destinations::Destinations},
+    shot::{capture::Captures, contract::ResponseSink, destinations::Destinations, job::BasePath},
 };
 use semigroup::Semigroup;
 use serde::{Deserialize, Serialize};
 use tonic::metadata::MetadataMap;
 
-#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize, Semigroup)]
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize, Semigroup)]
 #[serde(deny_unknown_fields, rename_all = "kebab-case")]
 #[semigroup(with = "semigroup::op::Coalesce")]
 pub struct GrpcResponse {
@@ -42,12 +44,15 @@ pub enum GrpcResponseMetadataMap {
     // Expect(ExpectEvaluator<tonic::metadata::MetadataMap>), // TODO serde
     Ignore,
 }
-#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
 #[serde(deny_unknown_fields, rename_all = "kebab-case")]
 pub enum GrpcResponseMessage {
     #[default]
     AnyOrEqual,
-    Value(ExpectEvaluator<serde_json::Value>),
+    Value(JsonEvaluator),
+    Noise(NoiseEvaluator),
+    Query(QueryEvaluator),
+    Schema(SchemaEvaluator),
 }
 
 impl<Se: Debug + Send + PartialEq + Serialize> ResponseSink<Result<tonic::Response<Se>, tonic::Status>>
@@ -58,9 +63,70 @@ impl<Se: Debug + Send + PartialEq + Serialize> ResponseSink<Result<tonic::Respon
     async fn consume(
         &self,
         msg: &mut Messages<Self::Message>,
+        captures: &mut Captures,
+        quorum: Option<&Quorum>,
         res: Destinations<Result<tonic::Response<Se>, tonic::Status>>,
     ) -> Result<(), Failure> {
-        self.evaluate(msg, res)
+        if !captures.is_empty() {
+            for (d, r) in res.iter().filter_map(|(d, r)| Some((d, r.as_ref().ok()?))) {
+                let metadata = |name: &str| Some(r.metadata().get(name)?.to_str().ok()?.to_string());
+                captures.capture_header(msg, d, metadata);
+                match serde_json::to_value(r.get_ref()) {
+                    Ok(message) => captures.capture_json(msg, d, &message),
+                    Err(e) => msg.warn(EvaluateError::boxed(e)),
+                }
+            }
+        }
+        match &self.message {
+            Some(GrpcResponseMessage::Value(JsonEvaluator::Patch(_)) | GrpcResponseMessage::Noise(_)) => {
+                let normalized = self.normalize(msg, res)?;
+                self.evaluate(msg, quorum, normalized)
+            }
+            _ => self.evaluate(msg, quorum, res),
+        }
+    }
+    fn with_base_path(mut self, base_path: &Option<BasePath>) -> Self {
+        self.message = self.message.map(|message| match message {
+            GrpcResponseMessage::Schema(schema) => GrpcResponseMessage::Schema(schema.with_base_path(base_path)),
+            message => message,
+        });
+        self
+    }
+}
+impl GrpcResponse {
+    /// Normalize messages as JSON by patches of [`JsonEvaluator`] or noise learned by [`NoiseEvaluator`].
+    #[allow(clippy::type_complexity)] // TODO
+    fn normalize<Se: Serialize>(
+        &self,
+        msg: &mut Messages<EvaluateError>,
+        res: Destinations<Result<tonic::Response<Se>, tonic::Status>>,
+    ) -> Result<Destinations<Result<tonic::Response<serde_json::Value>, tonic::Status>>, Failure> {
+        let (mut values, mut normalized) = (Destinations::default(), Destinations::default());
+        for (d, r) in res {
+            match r {
+                Ok(r) => {
+                    let (metadata, message, extensions) = r.into_parts();
+                    let value = serde_json::to_value(message).map_err(|e| msg.error(EvaluateError::boxed(e)))?;
+                    values.insert(d.clone(), value);
+                    normalized
+                        .insert(d, Ok(tonic::Response::from_parts(metadata, serde_json::Value::Null, extensions)));
+                }
+                Err(status) => {
+                    normalized.insert(d, Err(status));
+                }
+            }
+        }
+        let mut values = match &self.message {
+            Some(GrpcResponseMessage::Value(e)) => e.patch(msg, values)?,
+            Some(GrpcResponseMessage::Noise(noise)) => noise.cancel(msg, values)?,
+            _ => values,
+        };
+        for (d, r) in normalized.iter_mut() {
+            if let (Ok(r), Some(value)) = (r, values.swap_remove(d)) {
+                *r.get_mut() = value;
+            }
+        }
+        Ok(normalized)
     }
 }
 
@@ -72,7 +138,7 @@ impl<Se: PartialEq + Serialize> Evaluator<Result<tonic::Response<Se>, tonic::Sta
         res: &Result<tonic::Response<Se>, tonic::Status>,
     ) -> Result<(), Failure> {
         self.status.as_ref().unwrap_or(&Default::default()).evaluate_shot(msg, res)?;
-        let resp = res.as_ref().map_err(|_| Failure)?;
+        let resp = res.as_ref().map_err(|_| Failure::default())?;
         self.metadata_map.as_ref().unwrap_or(&Default::default()).evaluate_shot(msg, resp.metadata())?;
         self.message.as_ref().unwrap_or(&Default::default()).evaluate_shot(msg, resp.get_ref())?;
         Ok(())
@@ -84,8 +150,8 @@ impl<Se: PartialEq + Serialize> Evaluator<Result<tonic::Response<Se>, tonic::Sta
         res2: &Result<tonic::Response<Se>, tonic::Status>,
     ) -> Result<(), Failure> {
         self.status.as_ref().unwrap_or(&Default::default()).evaluate_compare(msg, res1, res2)?;
-        let resp1 = res1.as_ref().map_err(|_| Failure)?;
-        let resp2 = res2.as_ref().map_err(|_| Failure)?;
+        let resp1 = res1.as_ref().map_err(|_| Failure::default())?;
+        let resp2 = res2.as_ref().map_err(|_| Failure::default())?;
         self.metadata_map.as_ref().unwrap_or(&Default::default()).evaluate_compare(
             msg,
             resp1.metadata(),
@@ -164,18 +230,40 @@ impl<Se: PartialEq + Serialize> Evaluator<Se> for GrpcResponseMessage {
                 let resp = serde_json::to_value(res).map_err(|e| msg.error(EvaluateError::boxed(e)))?;
                 e.evaluate_shot(msg, &resp)
             }
+            Self::Noise(_) => Ok(()),
+            Self::Query(e) => {
+                let resp = serde_json::to_value(res).map_err(|e| msg.error(EvaluateError::boxed(e)))?;
+                e.evaluate_shot(msg, &resp)
+            }
+            Self::Schema(e) => {
+                let resp = serde_json::to_value(res).map_err(|e| msg.error(EvaluateError::boxed(e)))?;
+                e.evaluate_shot(msg, &resp)
+            }
         }
     }
     fn evaluate_compare(&self, msg: &mut Messages<Self::Message>, res1: &Se, res2: &Se) -> Result<(), Failure> {
         match self {
-            Self::AnyOrEqual => <Self as Evaluator<Se>>::evaluate_bool(self, msg, res1 == res2, |_| {
-                EvaluateError::custom("not equal message")
-            }),
+            Self::AnyOrEqual | Self::Noise(_) if res1 == res2 => Ok(()),
+            // noise is already removed from both messages
+            Self::AnyOrEqual | Self::Noise(_) => match (serde_json::to_value(res1), serde_json::to_value(res2)) {
+                (Ok(resp1), Ok(resp2)) if resp1 != resp2 => json::evaluate_equal(msg, &resp1, &resp2),
+                _ => Err(msg.error(EvaluateError::custom("not equal message"))),
+            },
             Self::Value(e) => {
                 let resp1 = serde_json::to_value(res1).map_err(|e| msg.error(EvaluateError::boxed(e)))?;
                 let resp2 = serde_json::to_value(res2).map_err(|e| msg.error(EvaluateError::boxed(e)))?;
                 e.evaluate_compare(msg, &resp1, &resp2)
             }
+            Self::Query(e) => {
+                let resp1 = serde_json::to_value(res1).map_err(|e| msg.error(EvaluateError::boxed(e)))?;
+                let resp2 = serde_json::to_value(res2).map_err(|e| msg.error(EvaluateError::boxed(e)))?;
+                e.evaluate_compare(msg, &resp1, &resp2)
+            }
+            Self::Schema(e) => {
+                let resp1 = serde_json::to_value(res1).map_err(|e| msg.error(EvaluateError::boxed(e)))?;
+                let resp2 = serde_json::to_value(res2).map_err(|e| msg.error(EvaluateError::boxed(e)))?;
+                e.evaluate_compare(msg, &resp1, &resp2)
+            }
         }
     }
 }
@@ -21,4 +21,39 @@ async fn test_example_yaml_config() {
     let report = job.shot::<_, _, DynamicContract<serde_json::Value, JsonSerializer>>(server, &spec).await.unwrap();
 
     assert!(report.evaluated.allow);
+    for (name, metric) in report.suites.iter().flat_map(|s| &s.metrics) {
+        assert!(metric.approx_response_bytes_quantile(0.5).is_some(), "response size of {name} is not recorded");
+    }
+}
+
+#[tokio::test]
+#[cfg(feature = "yaml")]
+async fn test_empty_response_message() {
+    let spec = JobSpec {
+        report_format: ReportFormat::NullDevice,
+        base_path: Some("..".parse().unwrap()),
+        ..Default::default()
+    };
+    let suite = serde_yaml::from_str(
+        r#"
+name: empty response message
+destinations:
+  test-api: http://localhost:50051
+contract:
+  proto-files:
+    protos: [./dev/server/grpc/proto/counter.proto]
+    includes: [./dev/server/grpc/proto]
+testcases:
+  # `Int64Value { value: 0 }` is encoded as an empty message
+  - target: counter.Counter/Reset
+    profile:
+      repeat: 2
+"#,
+    );
+    let job = Job(vec![suite.unwrap()]);
+
+    let server = Shared::new(RunCommand::default().app().routes());
+    let report = job.shot::<_, _, DynamicContract<serde_json::Value, JsonSerializer>>(server, &spec).await.unwrap();
+
+    assert!(report.evaluated.pass, "{:?}", report.suites[0].cases[0].messages);
 }
@@ -1,8 +1,12 @@
 use std::{convert::Infallible, marker::PhantomData};
 
-use relentless::shot::{
-    contract::{Contract, SignContract},
-    job::BasePath,
+use http_body::Body;
+use relentless::{
+    record::metric::BodySizeRecorder,
+    shot::{
+        contract::{Contract, SignContract},
+        job::BasePath,
+    },
 };
 use serde::{Deserialize, Serialize};
 use tower::{layer::util::Identity, Layer, Service};
@@ -35,8 +39,8 @@ impl<S, ReqB, ResB> Layer<S> for HttpContract<ReqB, ResB> {
 impl<S, ReqB, ResB> Contract<S> for HttpContract<ReqB, ResB>
 where
     S: Service<http::Request<ReqB>, Response = http::Response<ResB>> + Send,
-    ReqB: Send,
-    ResB: Send,
+    ReqB: Body + Send,
+    ResB: Body + Send,
 {
     type Sign = Self;
     type ReqSource = HttpRequest;
@@ -47,4 +51,15 @@ where
     type ResSink = HttpResponse;
 
     type SignError = Infallible;
+
+    fn status(response: &Self::Response) -> Option<u16> {
+        Some(response.status().as_u16())
+    }
+    fn request_size(request: &Self::Request) -> Option<u64> {
+        request.body().size_hint().exact()
+    }
+    /// The response body is not collected yet, so [`HttpResponse`] records its size after collecting it.
+    fn body_size(response: &mut Self::Response) -> BodySizeRecorder {
+        response.extensions_mut().get_or_insert_default::<BodySizeRecorder>().clone()
+    }
 }
@@ -4,7 +4,6 @@ use std::process::ExitCode;
 #[tokio::main]
 pub async fn main() -> Result<ExitCode, Box<dyn std::error::Error>> {
     use relentless::{
-        record::metric::MeasureLayer,
         report::Reporter,
         shot::job::{Cli, Job},
     };
@@ -13,13 +12,11 @@ pub async fn main() -> Result<ExitCode, Box<dyn std::error::Error>> {
     use tower::ServiceBuilder;
 
     Cli::run(|job: Job<_, _, _>, spec| async move {
-        let measure = MeasureLayer::new();
         let inject = OtelInjectLayer;
         let client = ReqwestClient::new().await?;
-        let service = ServiceBuilder::new().layer(&measure).layer(inject).service(client);
+        let service = ServiceBuilder::new().layer(inject).service(client);
         let report = job.shot::<_, _, HttpContract<Body, Body>>(tower::make::Shared::new(service), &spec).await?;
         spec.report(&report)?;
-        dbg!(measure.aggregated().times());
         Ok((!report.evaluated.assess().success() as u8).into())
     })
     .await
@@ -2,6 +2,7 @@ use std::fmt::Debug;
 
 use bytes::Bytes;
 use http_body::Body;
+use http_body_util::Full;
 use relentless::{http_newtype_serde, shot::contract::RequestSource, template::Template};
 use semigroup::Semigroup;
 use serde::{Deserialize, Serialize};
@@ -70,6 +71,21 @@ impl<ReqB: Body + Default + From<Bytes> + Debug> RequestSource<http::Request<Req
         request.headers_mut().extend(header);
         Ok(request)
     }
+
+    async fn dry_run(&self, destination: &http::Uri, target: &str, template: &Template) -> Result<String, Self::Error> {
+        let request: http::Request<Full<Bytes>> = self.produce(destination, target, template).await?;
+        let (parts, _) = request.into_parts();
+        let mut rendered = format!("{} {}\n", parts.method, parts.uri);
+        for (k, v) in parts.headers.iter() {
+            rendered.push_str(&format!("{k}: {}\n", String::from_utf8_lossy(v.as_bytes())));
+        }
+        let body = self.body.as_ref().unwrap_or(&Default::default()).render(template)?;
+        let body = RequestSource::<Full<Bytes>>::dry_run(&body, destination, target, template).await?;
+        if !body.is_empty() {
+            rendered.push_str(&format!("\n{body}\n"));
+        }
+        Ok(rendered)
+    }
 }
 
 impl<ReqB: Body + Default + From<Bytes>> RequestSource<ReqB> for HttpRequestBody {
@@ -85,4 +101,12 @@ impl<ReqB: Body + Default + From<Bytes>> RequestSource<ReqB> for HttpRequestBody
             }
         }
     }
+
+    async fn dry_run(&self, _: &http::Uri, _: &str, _: &Template) -> Result<String, Self::Error> {
+        match self {
+            Self::Empty => Ok(String::new()),
+            Self::Plaintext(s) => Ok(s.to_string()),
+            Self::Json(v) => serde_json::to_string(v).map_err(relentless::Error::boxed),
+        }
+    }
 }
@@ -1,24 +1,31 @@
-use std::fmt::{Debug, Display};
+use std::{
+    collections::BTreeMap,
+    fmt::{Debug, Display},
+};
 
 use bytes::Bytes;
 use futures::{StreamExt, TryStreamExt};
-use http::{HeaderMap, HeaderName, StatusCode};
+use http::{HeaderMap, HeaderName, HeaderValue, StatusCode};
 use http_body::Body;
 use http_body_util::BodyExt;
 use relentless::{
     error::EvaluateError,
     evaluator::{
-        evaluate::{Evaluator, Failure, Messages},
+        evaluate::{Evaluator, Failure, Messages, Quorum},
         expect::ExpectEvaluator,
-        plaintext::RegexEvaluator,
+        json::{self, JsonEvaluator, NoiseEvaluator},
+        plaintext::{RegexEvaluator, TextDiff},
+        query::QueryEvaluator,
+        schema::SchemaEvaluator,
     },
     http_newtype_serde,
-    shot::{contract::ResponseSink, destinations::Destinations},
+    record::metric::BodySizeRecorder,
+    shot::{capture::Captures, contract::ResponseSink, destinations::Destinations, job::BasePath},
 };
 use semigroup::Semigroup;
 use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize, Semigroup)]
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize, Semigroup)]
 #[serde(deny_unknown_fields, rename_all = "kebab-case")]
 #[semigroup(with = "semigroup::op::Coalesce")]
 pub struct HttpResponse {
@@ -38,24 +45,81 @@ pub enum HttpResponseStatus {
     #[default]
     OkOrEqual,
     Expect(ExpectEvaluator<http_newtype_serde::StatusCode>),
+    /// status class such as `4xx`
+    Class(HttpStatusClass),
+    /// inclusive range of status codes
+    Range {
+        min: http_newtype_serde::StatusCode,
+        max: http_newtype_serde::StatusCode,
+    },
+    OneOf(Vec<http_newtype_serde::StatusCode>),
+    /// like `ok-or-equal`, but destinations only need to be in the same class, such as 200 and 204
+    OkOrSameClass,
     Ignore,
 }
+/// Class of status codes such as `2xx`, `4xx`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct HttpStatusClass(u16);
+impl HttpStatusClass {
+    pub fn of(status: &StatusCode) -> Self {
+        Self(status.as_u16() / 100)
+    }
+    pub fn contains(&self, status: &StatusCode) -> bool {
+        Self::of(status) == *self
+    }
+}
+impl TryFrom<String> for HttpStatusClass {
+    type Error = String;
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        match value.to_ascii_lowercase().as_bytes() {
+            [c @ b'1'..=b'5', b'x', b'x'] => Ok(Self((c - b'0') as u16)),
+            _ => Err(format!("invalid status class `{value}`, such as `2xx` is expected")),
+        }
+    }
+}
+impl From<HttpStatusClass> for String {
+    fn from(value: HttpStatusClass) -> Self {
+        value.to_string()
+    }
+}
+impl Display for HttpStatusClass {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}xx", self.0)
+    }
+}
 #[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
 #[serde(deny_unknown_fields, rename_all = "kebab-case")]
 pub enum HttpResponseHeaders {
     #[default]
     AnyOrEqual,
-    // Allowlist(Vec<String>), // TODO
+    /// compare only these headers across destinations
+    Allowlist(Vec<String>),
+    /// compare all headers except these, such as `date` or `x-request-id`
+    Denylist(Vec<String>),
+    /// evaluate each header by its matcher, even with a single destination
+    Match(BTreeMap<String, HttpHeaderMatcher>),
     Expect(ExpectEvaluator<http_newtype_serde::HeaderMap>),
     Ignore,
 }
-#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+pub enum HttpHeaderMatcher {
+    Exact(String),
+    Regex(RegexEvaluator),
+    Present,
+    Absent,
+}
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
 #[serde(deny_unknown_fields, rename_all = "kebab-case")]
 pub enum HttpResponseBody {
     #[default]
     AnyOrEqual,
     Regex(RegexEvaluator),
-    Json(ExpectEvaluator<serde_json::Value>),
+    Json(JsonEvaluator),
+    Noise(NoiseEvaluator),
+    Query(QueryEvaluator),
+    Schema(SchemaEvaluator),
 }
 
 impl<ResB, E> ResponseSink<Result<http::Response<ResB>, E>> for HttpResponse
@@ -69,6 +133,8 @@ where
     async fn consume(
         &self,
         msg: &mut Messages<Self::Message>,
+        captures: &mut Captures,
+        quorum: Option<&Quorum>,
         res: Destinations<Result<http::Response<ResB>, E>>,
     ) -> Result<(), Failure> {
         let buffers = res.len().max(1);
@@ -76,13 +142,58 @@ where
             .map(|(d, r)| async {
                 let (parts, body) = r.map_err(EvaluateError::custom)?.into_parts();
                 let collected = body.collect().await.map_err(|_| EvaluateError::custom("failed to collect body"))?;
-                Ok::<_, EvaluateError>((d, http::Response::from_parts(parts, collected.to_bytes())))
+                let bytes = collected.to_bytes();
+                if let Some(recorder) = parts.extensions.get::<BodySizeRecorder>() {
+                    recorder.record_response(bytes.len() as u64);
+                }
+                Ok::<_, EvaluateError>((d, http::Response::from_parts(parts, bytes)))
             })
             .buffer_unordered(buffers)
             .try_collect()
             .await
             .map_err(|e| msg.error(e))?;
-        self.evaluate(msg, collected)
+        for (d, r) in collected.iter() {
+            let header = |name: &str| Some(r.headers().get(name)?.to_str().ok()?.to_string());
+            captures.capture_header(msg, d, header);
+            captures.capture_body(msg, d, r.body());
+        }
+        let collected = self.normalize(msg, collected)?;
+        self.evaluate(msg, quorum, collected)
+    }
+    fn with_base_path(mut self, base_path: &Option<BasePath>) -> Self {
+        self.body = self.body.map(|body| match body {
+            HttpResponseBody::Schema(schema) => HttpResponseBody::Schema(schema.with_base_path(base_path)),
+            body => body,
+        });
+        self
+    }
+}
+impl HttpResponse {
+    /// Normalize JSON bodies by patches of [`JsonEvaluator`] or noise learned by [`NoiseEvaluator`] before comparing them.
+    fn normalize(
+        &self,
+        msg: &mut Messages<EvaluateError>,
+        collected: Destinations<http::Response<Bytes>>,
+    ) -> Result<Destinations<http::Response<Bytes>>, Failure> {
+        if !matches!(&self.body, Some(HttpResponseBody::Json(JsonEvaluator::Patch(_)) | HttpResponseBody::Noise(_))) {
+            return Ok(collected);
+        }
+        let (mut parts, mut bodies) = (Vec::new(), Destinations::default());
+        for (d, r) in collected {
+            let (p, body) = r.into_parts();
+            let value: serde_json::Value =
+                serde_json::from_slice(&body).map_err(|e| msg.error(EvaluateError::boxed(e)))?;
+            parts.push(p);
+            bodies.insert(d, value);
+        }
+        let bodies = match &self.body {
+            Some(HttpResponseBody::Json(e)) => e.patch(msg, bodies)?,
+            Some(HttpResponseBody::Noise(noise)) => noise.cancel(msg, bodies)?,
+            _ => bodies,
+        };
+        let normalized =
+            parts.into_iter().zip(bodies).map(|(p, (d, v))| (d, http::Response::from_parts(p, v.to_string().into())));
+        Ok(normalized.collect())
     }
 }
 impl Evaluator<http::Response<Bytes>> for HttpResponse {
@@ -110,10 +221,20 @@ impl Evaluator<StatusCode> for HttpResponseStatus {
     type Message = EvaluateError;
     fn evaluate_shot(&self, msg: &mut Messages<Self::Message>, res: &StatusCode) -> Result<(), Failure> {
         match self {
-            Self::OkOrEqual => {
+            Self::OkOrEqual | Self::OkOrSameClass => {
                 self.evaluate_bool(msg, res.is_success(), |_| EvaluateError::custom("not success status"))
             }
             Self::Expect(e) => e.evaluate_shot(msg, res),
+            Self::Class(class) => self.evaluate_bool(msg, class.contains(res), |_| {
+                EvaluateError::custom(format!("status {res} is not {class}"))
+            }),
+            Self::Range { min, max } => self.evaluate_bool(msg, (**min..=**max).contains(res), |_| {
+                EvaluateError::custom(format!("status {res} is not in {}..={}", min.as_u16(), max.as_u16()))
+            }),
+            Self::OneOf(codes) => self.evaluate_bool(msg, codes.iter().any(|c| c == res), |_| {
+                let codes: Vec<_> = codes.iter().map(|c| c.as_u16()).collect();
+                EvaluateError::custom(format!("status {res} is not one of {codes:?}"))
+            }),
             Self::Ignore => Ok(()),
         }
     }
@@ -124,8 +245,20 @@ impl Evaluator<StatusCode> for HttpResponseStatus {
         res2: &StatusCode,
     ) -> Result<(), Failure> {
         match self {
-            Self::OkOrEqual => self.evaluate_bool(msg, res1 == res2, |_| EvaluateError::custom("not equal status")),
+            Self::OkOrEqual => {
+                self.evaluate_bool(msg, res1 == res2, |_| EvaluateError::custom(format!("status {res1} != {res2}")))
+            }
+            Self::OkOrSameClass => {
+                let (class1, class2) = (HttpStatusClass::of(res1), HttpStatusClass::of(res2));
+                self.evaluate_bool(msg, class1 == class2, |_| {
+                    EvaluateError::custom(format!("status class {class1} != {class2} ({res1} != {res2})"))
+                })
+            }
             Self::Expect(e) => e.evaluate_compare(msg, res1, res2),
+            Self::Class(_) | Self::Range { .. } | Self::OneOf(_) => {
+                self.evaluate_shot(msg, res1)?;
+                self.evaluate_shot(msg, res2)
+            }
             Self::Ignore => Ok(()),
         }
     }
@@ -134,7 +267,8 @@ impl Evaluator<HeaderMap> for HttpResponseHeaders {
     type Message = EvaluateError;
     fn evaluate_shot(&self, msg: &mut Messages<Self::Message>, res: &HeaderMap) -> Result<(), Failure> {
         match self {
-            Self::AnyOrEqual => Ok(()),
+            Self::AnyOrEqual | Self::Allowlist(_) | Self::Denylist(_) => Ok(()),
+            Self::Match(matchers) => self.evaluate_matchers(msg, matchers, res),
             Self::Expect(e) => e.evaluate_shot(msg, res),
             Self::Ignore => Ok(()),
         }
@@ -145,12 +279,13 @@ impl Evaluator<HeaderMap> for HttpResponseHeaders {
         res1: &HeaderMap,
         res2: &HeaderMap,
     ) -> Result<(), Failure> {
-        let (resp1, resp2): (HeaderMap, HeaderMap) = (
-            self.allowlist().filter_map(|k| Some((k.clone(), res1.get(&k)?.clone()))).collect(),
-            self.allowlist().filter_map(|k| Some((k.clone(), res2.get(&k)?.clone()))).collect(),
-        );
+        let (resp1, resp2) = (self.compared(res1), self.compared(res2));
         match self {
-            Self::AnyOrEqual => self.evaluate_bool(msg, resp1 == resp2, |_| EvaluateError::custom("not equal headers")),
+            Self::AnyOrEqual | Self::Allowlist(_) | Self::Denylist(_) => Self::evaluate_equal(msg, &resp1, &resp2),
+            Self::Match(matchers) => {
+                self.evaluate_matchers(msg, matchers, res1)?;
+                self.evaluate_matchers(msg, matchers, res2)
+            }
             Self::Expect(e) => e.evaluate_compare(msg, &resp1, &resp2),
             Self::Ignore => Ok(()),
         }
@@ -159,11 +294,72 @@ impl Evaluator<HeaderMap> for HttpResponseHeaders {
 impl HttpResponseHeaders {
     pub const DEFAULT_ALLOWLIST: &[&str] =
         &["content-type", "content-length", "content-language", "content-encoding", "cache-control"];
-    pub fn raw_allowlist(&self) -> &[&str] {
-        Self::DEFAULT_ALLOWLIST
+
+    /// Headers compared across destinations, only [`Self::DEFAULT_ALLOWLIST`] is compared without lists.
+    pub fn compared(&self, res: &HeaderMap) -> HeaderMap {
+        let listed = |names: &[String], name: &HeaderName| names.iter().any(|n| n.eq_ignore_ascii_case(name.as_str()));
+        let compared = res.iter().filter(|(name, _)| match self {
+            Self::Allowlist(names) => listed(names, name),
+            Self::Denylist(names) => !listed(names, name),
+            _ => Self::DEFAULT_ALLOWLIST.contains(&name.as_str()),
+        });
+        compared.map(|(name, value)| (name.clone(), value.clone())).collect()
+    }
+
+    /// Differing headers are reported as [`json::JsonDiff`] of objects keyed by header names,
+    /// `+` only in `res1` and `-` only in `res2`.
+    pub fn evaluate_equal(
+        msg: &mut Messages<EvaluateError>,
+        res1: &HeaderMap,
+        res2: &HeaderMap,
+    ) -> Result<(), Failure> {
+        let object = |res: &HeaderMap| {
+            let values = |name: &HeaderName| {
+                let values: Vec<_> = res.get_all(name).iter().map(|v| String::from_utf8_lossy(v.as_bytes())).collect();
+                serde_json::Value::String(values.join(", "))
+            };
+            serde_json::Value::Object(res.keys().map(|name| (name.to_string(), values(name))).collect())
+        };
+        json::evaluate_equal(msg, &object(res1), &object(res2))
+    }
+
+    pub fn evaluate_matchers(
+        &self,
+        msg: &mut Messages<EvaluateError>,
+        matchers: &BTreeMap<String, HttpHeaderMatcher>,
+        res: &HeaderMap,
+    ) -> Result<(), Failure> {
+        let failures: Vec<_> = matchers.iter().filter_map(|(name, m)| m.evaluate(name, res.get(name)).err()).collect();
+        let failed = !failures.is_empty();
+        failures.into_iter().for_each(|e| _ = msg.error(e));
+        if failed {
+            Err(Failure::default())
+        } else {
+            Ok(())
+        }
     }
-    pub fn allowlist(&self) -> impl '_ + Iterator<Item = HeaderName> {
-        self.raw_allowlist().iter().map(|&s| s.parse().unwrap_or_else(|_| unreachable!()))
+}
+impl HttpHeaderMatcher {
+    pub fn evaluate(&self, name: &str, value: Option<&HeaderValue>) -> Result<(), EvaluateError> {
+        let value = value.map(|v| String::from_utf8_lossy(v.as_bytes()));
+        match (self, value) {
+            (Self::Exact(expected), Some(v)) if v == *expected => Ok(()),
+            (Self::Exact(expected), Some(v)) => {
+                Err(EvaluateError::custom(format!("header {name}: {v:?} != {expected:?}")))
+            }
+            (Self::Regex(regex), Some(v)) => match regex.is_match(&v) {
+                Ok(true) => Ok(()),
+                Ok(false) => {
+                    Err(EvaluateError::custom(format!("header {name}: {v:?} does not match /{}/", regex.raw_regex())))
+                }
+                Err(e) => Err(EvaluateError::boxed(e)),
+            },
+            (Self::Exact(_) | Self::Regex(_) | Self::Present, None) => {
+                Err(EvaluateError::custom(format!("header {name} is absent")))
+            }
+            (Self::Present, Some(_)) | (Self::Absent, None) => Ok(()),
+            (Self::Absent, Some(v)) => Err(EvaluateError::custom(format!("header {name} should be absent: {v:?}"))),
+        }
     }
 }
 impl Evaluator<Bytes> for HttpResponseBody {
@@ -177,11 +373,34 @@ impl Evaluator<Bytes> for HttpResponseBody {
                     serde_json::from_slice(res).map_err(|e| msg.error(EvaluateError::boxed(e)))?;
                 e.evaluate_shot(msg, &resp)
             }
+            Self::Noise(_) => Ok(()),
+            Self::Query(e) => {
+                let resp: serde_json::Value =
+                    serde_json::from_slice(res).map_err(|e| msg.error(EvaluateError::boxed(e)))?;
+                e.evaluate_shot(msg, &resp)
+            }
+            Self::Schema(e) => {
+                let resp: serde_json::Value =
+                    serde_json::from_slice(res).map_err(|e| msg.error(EvaluateError::boxed(e)))?;
+                e.evaluate_shot(msg, &resp)
+            }
         }
     }
     fn evaluate_compare(&self, msg: &mut Messages<Self::Message>, res1: &Bytes, res2: &Bytes) -> Result<(), Failure> {
         match self {
-            Self::AnyOrEqual => self.evaluate_bool(msg, res1 == res2, |_| EvaluateError::custom("not equal body")),
+            Self::AnyOrEqual if res1 == res2 => Ok(()),
+            Self::AnyOrEqual => {
+                // JSON bodies are reported with differing paths and texts with unified diff,
+                // so that large bodies tell what differs
+                let json = |b: &Bytes| serde_json::from_slice::<serde_json::Value>(b).ok();
+                match (json(res1), json(res2)) {
+                    (Some(resp1), Some(resp2)) if resp1 != resp2 => json::evaluate_equal(msg, &resp1, &resp2),
+                    _ => match (std::str::from_utf8(res1), std::str::from_utf8(res2)) {
+                        (Ok(text1), Ok(text2)) => Err(msg.error(EvaluateError::TextDiff(TextDiff::new(text1, text2)))),
+                        _ => Err(msg.error(EvaluateError::custom("not equal body"))),
+                    },
+                }
+            }
             Self::Regex(e) => {
                 e.evaluate_compare(msg, &String::from_utf8_lossy(res1)[..], &String::from_utf8_lossy(res2)[..])
             }
@@ -192,6 +411,66 @@ impl Evaluator<Bytes> for HttpResponseBody {
                     serde_json::from_slice(res2).map_err(|e| msg.error(EvaluateError::boxed(e)))?;
                 e.evaluate_compare(msg, &resp1, &resp2)
             }
+            Self::Noise(_) => {
+                // noise is already removed from both bodies
+                let resp1: serde_json::Value =
+                    serde_json::from_slice(res1).map_err(|e| msg.error(EvaluateError::boxed(e)))?;
+                let resp2: serde_json::Value =
+                    serde_json::from_slice(res2).map_err(|e| msg.error(EvaluateError::boxed(e)))?;
+                json::evaluate_equal(msg, &resp1, &resp2)
+            }
+            Self::Query(e) => {
+                let resp1: serde_json::Value =
+                    serde_json::from_slice(res1).map_err(|e| msg.error(EvaluateError::boxed(e)))?;
+                let resp2: serde_json::Value =
+                    serde_json::from_slice(res2).map_err(|e| msg.error(EvaluateError::boxed(e)))?;
+                e.evaluate_compare(msg, &resp1, &resp2)
+            }
+            Self::Schema(e) => {
+                let resp1: serde_json::Value =
+                    serde_json::from_slice(res1).map_err(|e| msg.error(EvaluateError::boxed(e)))?;
+                let resp2: serde_json::Value =
+                    serde_json::from_slice(res2).map_err(|e| msg.error(EvaluateError::boxed(e)))?;
+                e.evaluate_compare(msg, &resp1, &resp2)
+            }
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_header_diff() {
+        let headers = |pairs: &[(&'static str, &'static str)]| -> HeaderMap {
+            pairs.iter().map(|(n, v)| (HeaderName::from_static(n), HeaderValue::from_static(v))).collect()
+        };
+        let res1 = headers(&[("content-type", "application/json"), ("cache-control", "no-cache")]);
+        let res2 = headers(&[("content-type", "text/plain"), ("content-language", "en")]);
+
+        let mut msg = Messages::new();
+        assert!(HttpResponseHeaders::evaluate_equal(&mut msg, &res1, &res1).is_ok());
+        assert!(HttpResponseHeaders::evaluate_equal(&mut msg, &res1, &res2).is_err());
+        let expected = [
+            "not equal json at 3 paths",
+            r#"+ /cache-control: "no-cache""#,
+            r#"~ /content-type: "application/json" != "text/plain""#,
+            r#"- /content-language: "en""#,
+        ];
+        assert_eq!(msg.to_string(), format!("{}\n", expected.join("\n")));
+    }
+
+    #[test]
+    #[cfg(feature = "yaml")]
+    fn test_typo_patch_fail() {
+        let response = |patch_fail| {
+            let yaml =
+                format!("body:\n  json:\n    patch: [{{op: remove, path: /at}}]\n    patch-fail: {patch_fail}\n");
+            serde_yaml::from_str::<HttpResponse>(&yaml)
+        };
+        let body = response("deny").unwrap().body;
+        assert!(matches!(body, Some(HttpResponseBody::Json(JsonEvaluator::Patch(_)))));
+        assert!(response("denyy").is_err());
+    }
+}
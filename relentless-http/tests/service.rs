@@ -8,7 +8,11 @@ use relentless_http::contract::HttpContract;
 #[tokio::test]
 #[cfg(feature = "yaml")]
 async fn test_example_yaml_config() {
-    let spec = JobSpec { report_format: ReportFormat::NullDevice, ..Default::default() };
+    let spec = JobSpec {
+        report_format: ReportFormat::NullDevice,
+        base_path: Some("..".parse().unwrap()),
+        ..Default::default()
+    };
     let files: Result<Vec<_>, _> = glob::glob("examples/config/*.yaml").unwrap().collect();
     let job = Job::from_files(&files.unwrap()).unwrap();
 
@@ -17,4 +21,44 @@ async fn test_example_yaml_config() {
     let report = job.shot::<_, _, HttpContract<Body, Body>>(make, &spec).await.unwrap();
 
     assert!(report.evaluated.allow);
+    for (name, metric) in report.suites.iter().flat_map(|s| &s.metrics) {
+        assert!(metric.approx_response_bytes_quantile(0.5).is_some(), "response size of {name} is not recorded");
+    }
+}
+
+#[tokio::test]
+#[cfg(feature = "yaml")]
+async fn test_dry_run_example_yaml_config() {
+    let spec = JobSpec { report_format: ReportFormat::NullDevice, dry_run: true, ..Default::default() };
+    let files: Result<Vec<_>, _> = glob::glob("examples/config/*.yaml").unwrap().collect();
+    let job = Job::from_files(&files.unwrap()).unwrap();
+
+    let service = relentless_http_dev_server::app::AppRouter::default().service();
+    let make = axum::ServiceExt::<axum::extract::Request>::into_make_service(service);
+    let report = job.shot::<_, _, HttpContract<Body, Body>>(make, &spec).await.unwrap();
+
+    assert!(report.evaluated.pass);
+    for case in report.suites.iter().flat_map(|s| &s.cases) {
+        assert!(!case.requests.is_empty(), "{} is not rendered", case.case.target);
+    }
+}
+
+#[tokio::test]
+#[cfg(feature = "yaml")]
+async fn test_chunked_response_body_size() {
+    let spec = JobSpec { report_format: ReportFormat::NullDevice, ..Default::default() };
+    let suite = serde_yaml::from_str(
+        "name: chunked\ndestinations: {test: http://localhost:3000}\ntestcases: [target: /chunked]",
+    );
+    let job = relentless::shot::job::Job(vec![suite.unwrap()]);
+
+    let chunks = || futures::stream::iter(["hello, ", "world"].map(Ok::<_, std::convert::Infallible>));
+    let router =
+        axum::Router::new().route("/chunked", axum::routing::get(move || async move { Body::from_stream(chunks()) }));
+    let make = router.into_make_service();
+    let report = job.shot::<_, _, HttpContract<Body, Body>>(make, &spec).await.unwrap();
+
+    assert!(report.evaluated.pass);
+    let metric = report.suites[0].cases[0].metrics.get("test").unwrap();
+    assert_eq!(metric.approx_response_bytes_quantile(0.5).map(|b| b.as_u64()), Some(12));
 }
@@ -92,6 +92,19 @@ pub enum EvaluateError {
     ShouldShot,
     NotOk,
     Timeout(Duration),
+    Compared {
+        target: String,
+        baseline: String,
+        source: Box<EvaluateError>,
+    },
+    Outlier {
+        destination: String,
+        agreed: usize,
+        total: usize,
+    },
+    #[cfg(feature = "json")]
+    JsonDiff(crate::evaluator::json::JsonDiff),
+    TextDiff(crate::evaluator::plaintext::TextDiff),
     Custom(String),
     Box(Box<dyn std::error::Error + Send + Sync + 'static>),
 }
@@ -107,6 +120,19 @@ impl MessageExt for EvaluateError {
     fn timeout(time: Duration) -> Self {
         Self::Timeout(time)
     }
+    fn custom<E: Display>(e: E) -> Self {
+        Self::Custom(e.to_string())
+    }
+    fn compared(self, target: &str, baseline: &str) -> Self {
+        Self::Compared { target: target.to_string(), baseline: baseline.to_string(), source: Box::new(self) }
+    }
+    fn text_diff(&self) -> Option<&crate::evaluator::plaintext::TextDiff> {
+        match self {
+            Self::TextDiff(diff) => Some(diff),
+            Self::Compared { source, .. } => source.text_diff(),
+            _ => None,
+        }
+    }
 }
 impl From<EvaluateError> for RelentlessError {
     fn from(value: EvaluateError) -> Self {
@@ -116,6 +142,7 @@ impl From<EvaluateError> for RelentlessError {
 impl std::error::Error for EvaluateError {
     fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
         match self {
+            Self::Compared { source, .. } => Some(source),
             Self::Box(e) => e.source(),
             _ => None,
         }
@@ -129,6 +156,13 @@ impl Display for EvaluateError {
             Self::ShouldShot => write!(f, "should shot"),
             Self::NotOk => write!(f, "not ok"),
             Self::Timeout(d) => write!(f, "request timed out after {d:?}"),
+            Self::Compared { target, baseline, source } => write!(f, "{target} vs {baseline}: {source}"),
+            #[cfg(feature = "json")]
+            Self::JsonDiff(diff) => write!(f, "{diff}"),
+            Self::TextDiff(diff) => write!(f, "{diff}"),
+            Self::Outlier { destination, agreed, total } => {
+                write!(f, "{destination} is an outlier, {agreed} of {total} destinations agree")
+            }
             Self::Custom(e) => write!(f, "{e}"),
             Self::Box(e) => write!(f, "{e}"),
         }
@@ -165,4 +199,11 @@ mod tests {
         let err = f().unwrap_err();
         assert!(matches!(err.error().downcast_ref().unwrap(), std::io::Error { .. }));
     }
+
+    #[test]
+    fn test_text_diff_of_message() {
+        let diff = EvaluateError::TextDiff(crate::evaluator::plaintext::TextDiff::new("a\n", "b\n"));
+        assert!(diff.compared("actual", "expect").text_diff().is_some());
+        assert!(EvaluateError::custom("@@ -1 +1 @@").text_diff().is_none());
+    }
 }
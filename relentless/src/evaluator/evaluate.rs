@@ -3,22 +3,28 @@ use std::{fmt::Display, time::Duration};
 use semigroup::Semigroup;
 use serde::{Deserialize, Serialize};
 
-use crate::{error::EvaluateError, shot::destinations::Destinations};
+use crate::{error::EvaluateError, evaluator::plaintext::TextDiff, shot::destinations::Destinations};
 
 // TODO error handling
 pub trait Evaluator<S: ?Sized> {
     type Message;
     fn evaluate_shot(&self, msg: &mut Messages<Self::Message>, res: &S) -> Result<(), Failure>;
     fn evaluate_compare(&self, msg: &mut Messages<Self::Message>, res1: &S, res2: &S) -> Result<(), Failure>;
-    fn evaluate(&self, msg: &mut Messages<Self::Message>, res: Destinations<S>) -> Result<(), Failure>
+    fn evaluate(
+        &self,
+        msg: &mut Messages<Self::Message>,
+        quorum: Option<&Quorum>,
+        res: Destinations<S>,
+    ) -> Result<(), Failure>
     where
-        Self::Message: From<EvaluateError>,
+        Self::Message: From<EvaluateError> + MessageExt,
         S: Sized,
     {
-        match res.len() {
-            0 => Err(EvaluateError::EmptyTarget).map_err(|e| msg.error(e.into()))?,
-            1 => self.evaluate_shots(msg, res),
-            _ => self.evaluate_compares(msg, res),
+        match (res.len(), quorum) {
+            (0, _) => Err(EvaluateError::EmptyTarget).map_err(|e| msg.error(e.into()))?,
+            (1, _) => self.evaluate_shots(msg, res),
+            (_, Some(quorum)) => self.evaluate_quorum(msg, quorum, res),
+            (_, None) => self.evaluate_compares(msg, res),
         }
     }
 
@@ -42,30 +48,100 @@ pub trait Evaluator<S: ?Sized> {
         let (_, resp) = res.into_iter().next().ok_or(EvaluateError::EmptyTarget).map_err(|e| msg.error(e.into()))?;
         self.evaluate_shot(msg, &resp)
     }
+    /// The first destination is the baseline, and each of the others is compared with it.
     fn evaluate_compares(&self, msg: &mut Messages<Self::Message>, res: Destinations<S>) -> Result<(), Failure>
     where
-        Self::Message: From<EvaluateError>,
+        Self::Message: From<EvaluateError> + MessageExt,
         S: Sized,
     {
-        let v: Vec<_> = res.into_iter().collect();
-        v.windows(2).try_fold((), |(), w| {
-            let ((_, a), (_, b)) = (&w[0], &w[1]);
-            self.evaluate_compare(msg, a, b)
-        })
+        let mut res = res.into_iter();
+        let (baseline, base) = res.next().ok_or(EvaluateError::EmptyTarget).map_err(|e| msg.error(e.into()))?;
+        // every pair is evaluated even if some of them fail, so that messages name all failed pairs
+        let evaluated: Vec<_> = res
+            .map(|(target, r)| {
+                let mut pair = Messages::new();
+                let evaluated = self.evaluate_compare(&mut pair, &r, &base);
+                msg.extend(pair.map(|m| m.compared(&target, &baseline)).0);
+                evaluated
+            })
+            .collect();
+        evaluated.into_iter().collect()
+    }
+
+    /// Compare every pair of destinations, and the answer that most destinations agree with becomes the reference.
+    /// Destinations that disagree with it are reported as outliers, and they are acceptable if the quorum agrees.
+    fn evaluate_quorum(
+        &self,
+        msg: &mut Messages<Self::Message>,
+        quorum: &Quorum,
+        res: Destinations<S>,
+    ) -> Result<(), Failure>
+    where
+        Self::Message: From<EvaluateError> + MessageExt,
+        S: Sized,
+    {
+        let res: Vec<_> = res.into_iter().collect();
+        let n = res.len();
+        let mut agreements = vec![vec![true; n]; n];
+        for i in 0..n {
+            for j in i + 1..n {
+                let agree = self.evaluate_compare(&mut Messages::new(), &res[i].1, &res[j].1).is_ok();
+                (agreements[i][j], agreements[j][i]) = (agree, agree);
+            }
+        }
+        // the baseline comes first, so it wins ties
+        let reference = (0..n).rev().max_by_key(|&i| agreements[i].iter().filter(|&&a| a).count()).unwrap_or_default();
+        let outliers: Vec<_> = (0..n).filter(|&i| !agreements[reference][i]).collect();
+        if outliers.is_empty() {
+            return Ok(());
+        }
+
+        let (agreed, acceptable) = (n - outliers.len(), n - outliers.len() >= quorum.0);
+        let kind = if acceptable { MessageKind::Warn } else { MessageKind::Error };
+        for i in outliers {
+            let ((target, r), (baseline, base)) = (&res[i], &res[reference]);
+            let mut pair = Messages::new();
+            let _ = self.evaluate_compare(&mut pair, r, base);
+            let outlier = EvaluateError::Outlier { destination: target.clone(), agreed, total: n }.into();
+            for message in pair.map(|m| m.compared(target, baseline)).0.into_iter().map(|m| m.message).chain([outlier])
+            {
+                msg.push(Message { message, kind: kind.clone() });
+            }
+        }
+        Err(Failure { acceptable })
     }
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Default, Hash)]
-pub struct Failure;
+pub struct Failure {
+    /// failed but acceptable, such as outliers of a comparison that the quorum agrees with
+    pub acceptable: bool,
+}
 impl std::error::Error for Failure {}
 impl Display for Failure {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "failure")
+        if self.acceptable {
+            write!(f, "acceptable failure")
+        } else {
+            write!(f, "failure")
+        }
     }
 }
 
+/// Minimum number of destinations that should agree with each other in N-way comparison, such as `2` of 3.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+pub struct Quorum(pub usize);
+
 pub trait MessageExt {
     fn timeout(time: Duration) -> Self;
+    fn custom<E: Display>(e: E) -> Self;
+    /// Name the pair of destinations, such as `canary vs baseline: status 500 != 200`.
+    fn compared(self, target: &str, baseline: &str) -> Self;
+    /// Diff of texts carried by the message, reporters highlight it as unified diff.
+    fn text_diff(&self) -> Option<&TextDiff> {
+        None
+    }
 }
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Default, Hash, Serialize, Deserialize)]
 pub struct Message<M> {
@@ -99,7 +175,7 @@ impl<T> Messages<T> {
     }
     pub fn error(&mut self, message: T) -> Failure {
         self.push(Message { message, kind: MessageKind::Error });
-        Failure
+        Failure::default()
     }
 }
 
@@ -113,6 +189,9 @@ impl<T> Messages<T> {
     pub fn len(&self) -> usize {
         self.0.len()
     }
+    pub fn map<U, F: FnMut(T) -> U>(self, mut f: F) -> Messages<U> {
+        Messages(self.0.into_iter().map(|Message { message, kind }| Message { message: f(message), kind }).collect())
+    }
     pub fn display_lines<'a>(&'a self) -> (impl 'a + Iterator<Item = &'a Message<T>>, Option<usize>) {
         let (n, m) = (self.0.len(), 3);
         let iter = self.0.iter().take(m);
@@ -132,3 +211,28 @@ impl<T> Extend<Message<T>> for Messages<T> {
         self.0.extend(iter)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use crate::testing::ValueResponseInner;
+
+    use super::*;
+
+    #[test]
+    fn test_quorum_marks_outlier() {
+        let res: Destinations<_> = [("old", json!(1)), ("new", json!(2)), ("replica", json!(1))].into_iter().collect();
+        let messages = "new vs old: not equal body\nnew is an outlier, 2 of 3 destinations agree\n";
+
+        let mut msg = Messages::new();
+        let evaluated = ValueResponseInner::AnyOrEqual.evaluate(&mut msg, Some(&Quorum(2)), res.clone());
+        assert_eq!(evaluated, Err(Failure { acceptable: true }));
+        assert_eq!(msg.to_string(), messages);
+
+        let mut msg = Messages::new();
+        let evaluated = ValueResponseInner::AnyOrEqual.evaluate(&mut msg, Some(&Quorum(3)), res);
+        assert_eq!(evaluated, Err(Failure { acceptable: false }));
+        assert_eq!(msg.to_string(), messages);
+    }
+}
@@ -1,5 +1,568 @@
-use serde::{Deserialize, Serialize};
+use std::{collections::HashSet, fmt::Display, time::Duration};
 
+use serde::{Deserialize, Deserializer, Serialize};
+use serde_json::Value;
+
+use crate::{
+    error::EvaluateError,
+    evaluator::{
+        evaluate::{Evaluator, Failure, MessageExt, Messages},
+        expect::ExpectEvaluator,
+    },
+    shot::destinations::Destinations,
+};
+
+/// Evaluator of JSON responses, `patch` normalizes them before comparison, `tolerance` relaxes the comparison,
+/// and other values are expected as they are.
+/// An expected object that has `patch` or `tolerance` key should be wrapped by `expect`, such as `{expect: {patch: 1}}`.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(untagged)]
+pub enum JsonEvaluator {
+    Patch(PatchEvaluator),
+    Tolerant(TolerantEvaluator),
+    Expect(ExpectEvaluator<Value>),
+}
+impl<'de> Deserialize<'de> for JsonEvaluator {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        // not `untagged`, it falls back to `Expect` silently even if `patch` or `tolerance` is misconfigured
+        let value = Value::deserialize(deserializer)?;
+        let evaluator = match &value {
+            Value::Object(o) if o.contains_key("patch") => PatchEvaluator::deserialize(value).map(Self::Patch),
+            Value::Object(o) if o.contains_key("tolerance") => {
+                TolerantEvaluator::deserialize(value).map(Self::Tolerant)
+            }
+            Value::Object(o) if o.len() == 1 && o.contains_key("expect") => {
+                Ok(Self::Expect(ExpectEvaluator::new(o["expect"].clone())))
+            }
+            _ => Ok(Self::Expect(ExpectEvaluator::new(value))),
+        };
+        evaluator.map_err(serde::de::Error::custom)
+    }
+}
+impl JsonEvaluator {
+    /// Apply patches to responses of each destination, responses are kept as they are if no patch is configured.
+    pub fn patch<M>(&self, msg: &mut Messages<M>, res: Destinations<Value>) -> Result<Destinations<Value>, Failure>
+    where
+        M: From<EvaluateError> + MessageExt,
+    {
+        match self {
+            Self::Patch(p) => p.patch(msg, res),
+            Self::Tolerant(_) | Self::Expect(_) => Ok(res),
+        }
+    }
+}
+impl Evaluator<Value> for JsonEvaluator {
+    type Message = EvaluateError;
+    fn evaluate_shot(&self, msg: &mut Messages<Self::Message>, res: &Value) -> Result<(), Failure> {
+        match self {
+            Self::Patch(_) | Self::Tolerant(_) => Ok(()),
+            Self::Expect(e) => evaluate_equal(msg, res, e.expected()),
+        }
+    }
+    fn evaluate_compare(&self, msg: &mut Messages<Self::Message>, res1: &Value, res2: &Value) -> Result<(), Failure> {
+        match self {
+            Self::Patch(PatchEvaluator { tolerance: Some(tolerance), .. })
+            | Self::Tolerant(TolerantEvaluator { tolerance }) => evaluate_tolerant(msg, res1, res2, tolerance),
+            Self::Patch(_) => evaluate_equal(msg, res1, res2),
+            Self::Expect(e) => {
+                evaluate_equal(msg, res1, e.expected())?;
+                evaluate_equal(msg, res2, e.expected())
+            }
+        }
+    }
+}
+
+/// RFC 6902 JSON patch applied to responses before comparison, such as removing timestamps.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+pub struct PatchEvaluator {
+    pub patch: JsonPatch,
+    #[serde(default)]
+    pub patch_fail: PatchFail,
+    #[serde(default)]
+    pub tolerance: Option<Tolerance>,
+}
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum JsonPatch {
+    All(json_patch::Patch),
+    Destinations(Destinations<json_patch::Patch>),
+}
 #[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
 #[serde(deny_unknown_fields, rename_all = "kebab-case")]
-pub struct JsonEvaluator {}
+pub enum PatchFail {
+    /// warn and compare the response without the patch
+    #[default]
+    Warn,
+    /// evaluate the response as failure
+    Deny,
+}
+impl PatchEvaluator {
+    pub fn patch<M>(&self, msg: &mut Messages<M>, res: Destinations<Value>) -> Result<Destinations<Value>, Failure>
+    where
+        M: From<EvaluateError> + MessageExt,
+    {
+        let mut patched = Destinations::default();
+        for (d, mut value) in res {
+            let patch = match &self.patch {
+                JsonPatch::All(patch) => Some(patch),
+                JsonPatch::Destinations(patches) => patches.get(&d),
+            };
+            if let Err(e) = patch.map(|p| json_patch::patch(&mut value, p)).transpose() {
+                let message = M::custom(format!("{d}: patch failed: {e}"));
+                match self.patch_fail {
+                    PatchFail::Warn => msg.warn(message),
+                    PatchFail::Deny => Err(msg.error(message))?,
+                }
+            }
+            patched.insert(d, value);
+        }
+        Ok(patched)
+    }
+}
+
+/// Comparison of JSON responses that tolerates semantically identical differences, such as `1.0` and `1`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+pub struct TolerantEvaluator {
+    pub tolerance: Tolerance,
+}
+/// Numbers are always compared by their values, so `1.0` equals `1` even without `epsilon`.
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+pub struct Tolerance {
+    /// JSONPath of arrays compared as multisets, such as `$.items` or `$.users[*].roles`
+    #[serde(default)]
+    pub unordered: Vec<serde_json_path::JsonPath>,
+    #[cfg_attr(feature = "yaml", serde(with = "serde_yaml::with::singleton_map_recursive"))]
+    #[serde(default)]
+    pub epsilon: Option<Epsilon>,
+    /// RFC 3339 timestamps are equal if they are within this window
+    #[serde(default)]
+    pub timestamp_window: Option<Duration>,
+}
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+pub enum Epsilon {
+    Absolute(f64),
+    /// relative to the larger absolute value
+    Relative(f64),
+}
+impl Tolerance {
+    /// JSON pointers of arrays that `unordered` found in the value.
+    pub fn unordered_pointers(&self, value: &Value) -> HashSet<String> {
+        let mut pointers = HashSet::new();
+        for path in &self.unordered {
+            pointers.extend(path.query_located(value).locations().map(|l| l.to_json_pointer()));
+        }
+        pointers
+    }
+
+    /// Equality of scalar values, numbers within `epsilon` and timestamps within `timestamp_window` are equal.
+    pub fn equal(&self, left: &Value, right: &Value) -> bool {
+        match (left, right) {
+            (Value::Number(l), Value::Number(r)) => match (l.as_f64(), r.as_f64()) {
+                (Some(l), Some(r)) => self.epsilon.as_ref().map_or(l == r, |e| e.within(l, r)),
+                _ => l == r,
+            },
+            (Value::String(l), Value::String(r)) if l != r => {
+                let parse = chrono::DateTime::parse_from_rfc3339;
+                match (&self.timestamp_window, parse(l), parse(r)) {
+                    (Some(window), Ok(l), Ok(r)) => (l - r).abs().to_std().is_ok_and(|d| d <= *window),
+                    _ => false,
+                }
+            }
+            (l, r) => l == r,
+        }
+    }
+}
+impl Epsilon {
+    pub fn within(&self, left: f64, right: f64) -> bool {
+        match self {
+            Self::Absolute(e) => (left - right).abs() <= *e,
+            Self::Relative(e) => (left - right).abs() <= e * left.abs().max(right.abs()),
+        }
+    }
+}
+
+/// Differential comparison like Diffy, the baseline and its `replica` run the same known-good version.
+/// Fields that differ between them, such as timestamps or random ids, are learned as noise and ignored.
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+pub struct NoiseEvaluator {
+    pub replica: String,
+}
+impl NoiseEvaluator {
+    /// JSON pointers that differ between the baseline (the first destination) and the replica.
+    pub fn learn<M>(&self, msg: &mut Messages<M>, res: &Destinations<Value>) -> Option<Vec<String>>
+    where
+        M: From<EvaluateError> + MessageExt,
+    {
+        let Some((baseline, base)) = res.first() else {
+            return Some(Vec::new());
+        };
+        match res.get(&self.replica) {
+            Some(replica) if baseline != &self.replica => Some(diff(base, replica)),
+            _ => {
+                let message = format!("replica `{}` should be a destination other than the baseline", self.replica);
+                msg.error(M::custom(message));
+                None
+            }
+        }
+    }
+
+    /// Remove the learned noise from responses of every destination.
+    pub fn cancel<M>(&self, msg: &mut Messages<M>, res: Destinations<Value>) -> Result<Destinations<Value>, Failure>
+    where
+        M: From<EvaluateError> + MessageExt,
+    {
+        let noise = self.learn(msg, &res).ok_or_else(Failure::default)?;
+        Ok(res.into_iter().map(|(d, v)| (d, noise.iter().fold(v, |v, p| remove(v, p)))).collect())
+    }
+}
+
+/// JSON pointers of fields that differ between `a` and `b`, such as `/data/0/id`.
+pub fn diff(a: &Value, b: &Value) -> Vec<String> {
+    JsonDiff::new(a, b).0.into_iter().map(JsonDiffEntry::into_pointer).collect()
+}
+
+/// Evaluate equality of JSON values, the differing paths are reported as [`JsonDiff`] instead of a whole value.
+pub fn evaluate_equal<M: From<EvaluateError>>(
+    msg: &mut Messages<M>,
+    left: &Value,
+    right: &Value,
+) -> Result<(), Failure> {
+    evaluate_diff(msg, JsonDiff::new(left, right))
+}
+
+/// Evaluate equality of JSON values with [`Tolerance`], the differing paths are reported as [`JsonDiff`].
+pub fn evaluate_tolerant<M: From<EvaluateError>>(
+    msg: &mut Messages<M>,
+    left: &Value,
+    right: &Value,
+    tolerance: &Tolerance,
+) -> Result<(), Failure> {
+    let unordered = tolerance.unordered_pointers(left);
+    evaluate_diff(msg, JsonDiff::tolerant(left, right, tolerance, &unordered))
+}
+
+fn evaluate_diff<M: From<EvaluateError>>(msg: &mut Messages<M>, diff: JsonDiff) -> Result<(), Failure> {
+    if diff.is_empty() {
+        Ok(())
+    } else {
+        Err(msg.error(EvaluateError::JsonDiff(diff).into()))
+    }
+}
+
+/// Structured difference of JSON values, `left` is the evaluated response and `right` is the baseline or expected one.
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct JsonDiff(pub Vec<JsonDiffEntry>);
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum JsonDiffEntry {
+    Changed { pointer: String, left: Value, right: Value },
+    Added { pointer: String, value: Value },
+    Removed { pointer: String, value: Value },
+}
+impl JsonDiff {
+    /// Values of the entries longer than this are truncated in display.
+    pub const DISPLAY_VALUE_LIMIT: usize = 80;
+
+    pub fn new(left: &Value, right: &Value) -> Self {
+        let mut entries = Vec::new();
+        Self::diff_at(left, right, String::new(), None, &mut entries);
+        Self(entries)
+    }
+    /// Difference with [`Tolerance`], arrays at `unordered` pointers of `left` are compared as multisets.
+    pub fn tolerant(left: &Value, right: &Value, tolerance: &Tolerance, unordered: &HashSet<String>) -> Self {
+        let mut entries = Vec::new();
+        Self::diff_at(left, right, String::new(), Some((tolerance, unordered)), &mut entries);
+        Self(entries)
+    }
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    fn diff_at(
+        left: &Value,
+        right: &Value,
+        pointer: String,
+        tolerance: Option<(&Tolerance, &HashSet<String>)>,
+        entries: &mut Vec<JsonDiffEntry>,
+    ) {
+        let entry = |pointer, l: Option<&Value>, r: Option<&Value>| match (l, r) {
+            (Some(left), None) => Some(JsonDiffEntry::Added { pointer, value: left.clone() }),
+            (None, Some(right)) => Some(JsonDiffEntry::Removed { pointer, value: right.clone() }),
+            _ => None,
+        };
+        match (left, right) {
+            (Value::Object(l), Value::Object(r)) => {
+                for k in l.keys().chain(r.keys().filter(|k| !l.contains_key(*k))) {
+                    let p = format!("{pointer}/{}", k.replace('~', "~0").replace('/', "~1"));
+                    match (l.get(k), r.get(k)) {
+                        (Some(l), Some(r)) => Self::diff_at(l, r, p, tolerance, entries),
+                        (l, r) => entries.extend(entry(p, l, r)),
+                    }
+                }
+            }
+            (Value::Array(l), Value::Array(r)) => {
+                let r = match tolerance {
+                    Some((t, unordered)) if unordered.contains(&pointer) => Self::align(l, r, &pointer, (t, unordered)),
+                    _ => r.iter().map(Some).collect(),
+                };
+                for i in 0..l.len().max(r.len()) {
+                    let p = format!("{pointer}/{i}");
+                    match (l.get(i), r.get(i).copied().flatten()) {
+                        (Some(l), Some(r)) => Self::diff_at(l, r, p, tolerance, entries),
+                        (l, r) => entries.extend(entry(p, l, r)),
+                    }
+                }
+            }
+            (l, r) if !tolerance.map_or(l == r, |(t, _)| t.equal(l, r)) => {
+                entries.push(JsonDiffEntry::Changed { pointer, left: l.clone(), right: r.clone() })
+            }
+            _ => {}
+        }
+    }
+
+    /// Reorder elements of `right` to match equal elements of `left`, unmatched ones fill the remaining positions.
+    fn align<'a>(
+        left: &[Value],
+        right: &'a [Value],
+        pointer: &str,
+        tolerance: (&Tolerance, &HashSet<String>),
+    ) -> Vec<Option<&'a Value>> {
+        let mut rest: Vec<_> = right.iter().map(Some).collect();
+        let mut aligned: Vec<_> = left
+            .iter()
+            .enumerate()
+            .map(|(i, l)| {
+                let equal = |r: &Value| {
+                    let mut entries = Vec::new();
+                    Self::diff_at(l, r, format!("{pointer}/{i}"), Some(tolerance), &mut entries);
+                    entries.is_empty()
+                };
+                let found = rest.iter().position(|r| r.is_some_and(equal))?;
+                rest[found].take()
+            })
+            .collect();
+        let mut unmatched = rest.into_iter().flatten();
+        aligned.iter_mut().filter(|a| a.is_none()).for_each(|a| *a = unmatched.next());
+        aligned.into_iter().chain(unmatched.map(Some)).collect()
+    }
+}
+impl JsonDiffEntry {
+    pub fn pointer(&self) -> &str {
+        match self {
+            Self::Changed { pointer, .. } | Self::Added { pointer, .. } | Self::Removed { pointer, .. } => pointer,
+        }
+    }
+    pub fn into_pointer(self) -> String {
+        match self {
+            Self::Changed { pointer, .. } | Self::Added { pointer, .. } | Self::Removed { pointer, .. } => pointer,
+        }
+    }
+}
+impl Display for JsonDiff {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let n = self.0.len();
+        write!(f, "not equal json at {n} {}", if n == 1 { "path" } else { "paths" })?;
+        self.0.iter().try_for_each(|e| write!(f, "\n{e}"))
+    }
+}
+impl Display for JsonDiffEntry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let truncated = |v: &Value| {
+            let s = v.to_string();
+            match s.char_indices().nth(JsonDiff::DISPLAY_VALUE_LIMIT) {
+                Some((i, _)) => format!("{}...", &s[..i]),
+                None => s,
+            }
+        };
+        let pointer = if self.pointer().is_empty() { "(root)" } else { self.pointer() };
+        match self {
+            Self::Changed { left, right, .. } => write!(f, "~ {pointer}: {} != {}", truncated(left), truncated(right)),
+            Self::Added { value, .. } => write!(f, "+ {pointer}: {}", truncated(value)),
+            Self::Removed { value, .. } => write!(f, "- {pointer}: {}", truncated(value)),
+        }
+    }
+}
+
+/// Remove the field of the pointer, elements of arrays are replaced with `null` to keep indices of the others.
+fn remove(mut value: Value, pointer: &str) -> Value {
+    let Some((parent, token)) = pointer.rsplit_once('/') else {
+        return Value::Null; // the root is noise
+    };
+    let token = token.replace("~1", "/").replace("~0", "~");
+    match value.pointer_mut(parent) {
+        Some(Value::Object(o)) => {
+            o.remove(&token);
+        }
+        Some(Value::Array(a)) => {
+            if let Some(v) = token.parse().ok().and_then(|i: usize| a.get_mut(i)) {
+                *v = Value::Null;
+            }
+        }
+        _ => {}
+    }
+    value
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_json_diff_display() {
+        let items: Vec<_> = (0..10000).map(|i| json!({"id": i, "name": format!("item {i}")})).collect();
+        let (mut left, right) =
+            (json!({"items": items.clone(), "extra": 1}), json!({"items": items, "missing": [1, 2]}));
+        left["items"][4321]["name"] = json!("changed");
+
+        let diff = JsonDiff::new(&left, &right);
+        let changed = JsonDiffEntry::Changed {
+            pointer: "/items/4321/name".to_string(),
+            left: json!("changed"),
+            right: json!("item 4321"),
+        };
+        assert_eq!(diff.0[1], changed);
+        let expected = [
+            "not equal json at 3 paths",
+            "+ /extra: 1",
+            r#"~ /items/4321/name: "changed" != "item 4321""#,
+            "- /missing: [1,2]",
+        ];
+        assert_eq!(diff.to_string(), expected.join("\n"));
+
+        let long = JsonDiff::new(&json!("a".repeat(100)), &json!(null)).to_string();
+        assert!(long.ends_with(&format!("~ (root): \"{}... != null", "a".repeat(79))));
+    }
+
+    #[test]
+    fn test_diff_pointers() {
+        let a = json!({"id": 1, "name": "a", "items": [{"at": 10}, {"at": 20}], "a/b": 1});
+        let b = json!({"id": 2, "name": "a", "items": [{"at": 10}, {"at": 21}, {"at": 30}], "extra": true});
+        assert_eq!(diff(&a, &b), ["/a~1b", "/id", "/items/1/at", "/items/2", "/extra"]);
+        assert!(diff(&a, &a).is_empty());
+    }
+
+    #[test]
+    fn test_patch_each_destination() {
+        let json = json!({
+            "patch": {
+                "server1": [{"op": "test", "path": "/server", "value": "server1"}, {"op": "remove", "path": "/server"}],
+                "server2": [{"op": "test", "path": "/server", "value": "server2"}, {"op": "remove", "path": "/server"}],
+            },
+            "patch-fail": "deny",
+        });
+        let evaluator: JsonEvaluator = serde_json::from_value(json).unwrap();
+        let res: Destinations<_> =
+            [("server1", json!({"server": "server1", "n": 1})), ("server2", json!({"server": "server2", "n": 1}))]
+                .into_iter()
+                .collect();
+        let mut msg = Messages::<EvaluateError>::new();
+
+        let patched = evaluator.patch(&mut msg, res.clone()).unwrap();
+        assert!(evaluator.evaluate(&mut msg, None, patched).is_ok());
+        assert!(msg.is_empty());
+
+        let swapped = res.into_iter().map(|(d, v)| (if d == "server1" { "server2" } else { "server1" }, v)).collect();
+        assert!(evaluator.patch(&mut msg, swapped).is_err());
+        assert_eq!(msg.len(), 1);
+    }
+
+    #[test]
+    fn test_expect_without_patch() {
+        let evaluator: JsonEvaluator = serde_json::from_value(json!({"key": "value"})).unwrap();
+        assert_eq!(evaluator, JsonEvaluator::Expect(ExpectEvaluator::new(json!({"key": "value"}))));
+
+        let evaluator: JsonEvaluator =
+            serde_json::from_value(json!({"patch": [{"op": "remove", "path": "/a"}]})).unwrap();
+        let mut msg = Messages::<EvaluateError>::new();
+        let patched = evaluator.patch(&mut msg, [("test", json!({"b": 1}))].into_iter().collect()).unwrap();
+        assert_eq!(patched["test"], json!({"b": 1}));
+        assert_eq!(msg.len(), 1); // warned by default
+    }
+
+    #[test]
+    fn test_misconfigured_patch() {
+        let typo = json!({"patch": [{"op": "remove", "path": "/a"}], "patch-fail": "denyy"});
+        assert!(serde_json::from_value::<JsonEvaluator>(typo).is_err());
+        let unknown = json!({"patch": [{"op": "remove", "path": "/a"}], "patch-fial": "deny"});
+        assert!(serde_json::from_value::<JsonEvaluator>(unknown).is_err());
+        let tolerance = json!({"tolerance": {"unorderd": ["$.tags"]}});
+        assert!(serde_json::from_value::<JsonEvaluator>(tolerance).is_err());
+        let path = json!({"tolerance": {"unordered": ["$.tags["]}});
+        assert!(serde_json::from_value::<JsonEvaluator>(path).is_err());
+
+        let evaluator: JsonEvaluator = serde_json::from_value(json!({"expect": {"patch": 1}})).unwrap();
+        assert_eq!(evaluator, JsonEvaluator::Expect(ExpectEvaluator::new(json!({"patch": 1}))));
+    }
+
+    #[test]
+    fn test_tolerant_compare() {
+        let json = json!({
+            "tolerance": {
+                "unordered": ["$.tags", "$.users[*].roles"],
+                "epsilon": {"absolute": 0.01},
+                "timestamp-window": {"secs": 5, "nanos": 0},
+            },
+        });
+        let evaluator: JsonEvaluator = serde_json::from_value(json).unwrap();
+        let left = json!({
+            "tags": ["a", "b", "c"],
+            "users": [{"id": 1, "roles": ["admin", "dev"]}],
+            "price": 1.0,
+            "at": "2025-01-01T00:00:00Z",
+        });
+        let right = json!({
+            "tags": ["c", "a", "b"],
+            "users": [{"id": 1, "roles": ["dev", "admin"]}],
+            "price": 1.001,
+            "at": "2025-01-01T09:00:03+09:00",
+        });
+        let mut msg = Messages::<EvaluateError>::new();
+        assert!(evaluator.evaluate_compare(&mut msg, &left, &right).is_ok());
+        assert!(msg.is_empty());
+
+        let right = json!({
+            "tags": ["d", "a", "b", "e"],
+            "users": [{"id": 1, "roles": ["dev", "admin"]}],
+            "price": 1.1,
+            "at": "2025-01-01T00:00:10Z",
+        });
+        assert!(evaluator.evaluate_compare(&mut msg, &left, &right).is_err());
+        let expected = [
+            "not equal json at 4 paths",
+            r#"~ /at: "2025-01-01T00:00:00Z" != "2025-01-01T00:00:10Z""#,
+            "~ /price: 1.0 != 1.1",
+            r#"~ /tags/2: "c" != "d""#,
+            r#"- /tags/3: "e""#,
+        ];
+        assert_eq!(msg.to_string(), expected.join("\n") + "\n");
+    }
+
+    #[test]
+    fn test_cancel_noise() {
+        let noise = NoiseEvaluator { replica: "replica".to_string() };
+        let res: Destinations<_> = [
+            ("baseline", json!({"id": "x", "now": 1, "items": [1, 2], "name": "a"})),
+            ("replica", json!({"id": "y", "now": 2, "items": [1, 3], "name": "a"})),
+            ("candidate", json!({"id": "z", "now": 3, "items": [1, 4], "name": "b"})),
+        ]
+        .into_iter()
+        .collect();
+        let mut msg = Messages::<EvaluateError>::new();
+
+        let cancelled = noise.cancel(&mut msg, res).unwrap();
+        assert_eq!(cancelled["baseline"], json!({"items": [1, null], "name": "a"}));
+        assert_eq!(cancelled["candidate"], json!({"items": [1, null], "name": "b"}));
+        assert!(msg.is_empty());
+
+        let unknown = NoiseEvaluator { replica: "unknown".to_string() };
+        assert!(unknown.cancel(&mut msg, cancelled).is_err());
+        assert_eq!(msg.len(), 1);
+    }
+}
@@ -1,4 +1,9 @@
 pub mod evaluate;
 pub mod expect;
+#[cfg(feature = "json")]
 pub mod json;
 pub mod plaintext;
+#[cfg(feature = "json")]
+pub mod query;
+#[cfg(feature = "json")]
+pub mod schema;
@@ -1,3 +1,5 @@
+use std::{fmt::Display, time::Duration};
+
 use regex::Regex;
 use serde::{Deserialize, Serialize};
 
@@ -36,3 +38,71 @@ impl Evaluator<str> for RegexEvaluator {
         Ok(())
     }
 }
+
+/// Line-based unified diff of plaintext, `-` lines are of the baseline or expected one and `+` lines are of the response.
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct TextDiff(String);
+impl TextDiff {
+    pub const CONTEXT_LINES: usize = 3;
+    /// Lines of the diff more than this are omitted in display.
+    pub const DISPLAY_LINE_LIMIT: usize = 50;
+    /// Characters of each line more than this are truncated in display, such as minified HTML.
+    pub const DISPLAY_CHAR_LIMIT: usize = 200;
+    /// Huge bodies may take long to diff, so the diff becomes coarser after this.
+    pub const TIMEOUT: Duration = Duration::from_millis(100);
+
+    pub fn new(left: &str, right: &str) -> Self {
+        let diff = similar::TextDiff::configure().timeout(Self::TIMEOUT).diff_lines(right, left);
+        let unified = diff.unified_diff().context_radius(Self::CONTEXT_LINES).to_string();
+        Self(unified)
+    }
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+    pub fn unified(&self) -> &str {
+        &self.0
+    }
+}
+impl Display for TextDiff {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "not equal body")?;
+        let lines = self.0.lines();
+        for line in lines.clone().take(Self::DISPLAY_LINE_LIMIT) {
+            match line.char_indices().nth(Self::DISPLAY_CHAR_LIMIT) {
+                Some((i, _)) => write!(f, "\n{}...", &line[..i])?,
+                None => write!(f, "\n{line}")?,
+            }
+        }
+        match lines.count().saturating_sub(Self::DISPLAY_LINE_LIMIT) {
+            0 => Ok(()),
+            n => write!(f, "\n... and {n} more lines"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_text_diff() {
+        let (baseline, response) = ("<ul>\n<li>a</li>\n<li>b</li>\n</ul>\n", "<ul>\n<li>a</li>\n<li>c</li>\n</ul>\n");
+        let diff = TextDiff::new(response, baseline);
+        let expected =
+            ["not equal body", "@@ -1,4 +1,4 @@", " <ul>", " <li>a</li>", "-<li>b</li>", "+<li>c</li>", " </ul>"];
+        assert_eq!(diff.to_string(), expected.join("\n"));
+        assert!(TextDiff::new(baseline, baseline).is_empty());
+    }
+
+    #[test]
+    fn test_text_diff_truncated() {
+        let baseline: String = (0..1000).map(|i| format!("line {i}\n")).collect();
+        let response = baseline.replace("line", "row");
+        let diff = TextDiff::new(&response, &baseline).to_string();
+        assert_eq!(diff.lines().count(), 1 + TextDiff::DISPLAY_LINE_LIMIT + 1);
+        assert!(diff.ends_with("... and 1951 more lines"));
+
+        let long = TextDiff::new(&"a".repeat(1000), "b").to_string();
+        assert!(long.contains(&format!("+{}...", "a".repeat(TextDiff::DISPLAY_CHAR_LIMIT - 1))));
+    }
+}
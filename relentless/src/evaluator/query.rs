@@ -0,0 +1,272 @@
+use std::{cmp::Ordering, fmt::Display};
+
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use serde_json_path::JsonPath;
+
+use crate::{
+    error::EvaluateError,
+    evaluator::evaluate::{Evaluator, Failure, Messages},
+};
+
+/// Assertions of JSONPath queries, only the queried fields are evaluated instead of a whole document.
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+pub struct QueryEvaluator(Vec<Query>);
+impl QueryEvaluator {
+    pub fn new(queries: impl IntoIterator<Item = Query>) -> Self {
+        Self(queries.into_iter().collect())
+    }
+    pub fn queries(&self) -> &[Query] {
+        &self.0
+    }
+}
+impl Evaluator<Value> for QueryEvaluator {
+    type Message = EvaluateError;
+    fn evaluate_shot(&self, msg: &mut Messages<Self::Message>, res: &Value) -> Result<(), Failure> {
+        let failures: Vec<_> = self.queries().iter().filter_map(|q| q.evaluate(res).err()).collect();
+        let failed = !failures.is_empty();
+        failures.into_iter().for_each(|e| _ = msg.error(e));
+        if failed {
+            Err(Failure::default())
+        } else {
+            Ok(())
+        }
+    }
+    fn evaluate_compare(&self, msg: &mut Messages<Self::Message>, res1: &Value, res2: &Value) -> Result<(), Failure> {
+        self.evaluate_shot(msg, res1)?;
+        self.evaluate_shot(msg, res2)
+    }
+}
+
+/// JSONPath query with an optional comparison, such as `$.items.length() > 0` or `$.user.name == "John Doe"`.
+/// The right hand side is a JSON literal and `=~` matches a regex. Without comparison, the path should exist.
+/// Every value found by the path should satisfy the comparison.
+/// It is parsed when deserialized, so that a malformed query fails to load the config.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct Query {
+    raw: String,
+    parsed: ParsedQuery,
+}
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Operator {
+    Eq,
+    Ne,
+    Gt,
+    Ge,
+    Lt,
+    Le,
+    Match,
+}
+#[derive(Debug, Clone)]
+pub struct ParsedQuery {
+    pub path: JsonPath,
+    pub length: bool,
+    pub comparison: Option<Comparison>,
+}
+#[derive(Debug, Clone)]
+pub enum Comparison {
+    Value(Operator, Value),
+    Regex(Regex),
+}
+impl TryFrom<String> for Query {
+    type Error = EvaluateError;
+    fn try_from(raw: String) -> Result<Self, Self::Error> {
+        let parsed = Self::parse(&raw)?;
+        Ok(Self { raw, parsed })
+    }
+}
+impl From<Query> for String {
+    fn from(query: Query) -> Self {
+        query.raw
+    }
+}
+impl PartialEq for Query {
+    fn eq(&self, other: &Self) -> bool {
+        self.raw == other.raw
+    }
+}
+impl Eq for Query {}
+impl Query {
+    pub const LENGTH_SUFFIX: &str = ".length()";
+
+    pub fn new(raw_query: impl Into<String>) -> Result<Self, EvaluateError> {
+        raw_query.into().try_into()
+    }
+    pub fn raw_query(&self) -> &str {
+        &self.raw
+    }
+    pub fn parsed(&self) -> &ParsedQuery {
+        &self.parsed
+    }
+
+    pub fn parse(raw_query: &str) -> Result<ParsedQuery, EvaluateError> {
+        let query = raw_query.trim();
+        let invalid = |e: &dyn Display| EvaluateError::custom(format!("invalid query `{raw_query}`: {e}"));
+        // filters of JSONPath may contain operators too, so the rightmost split that both sides are valid is used
+        let mut splits: Vec<_> = Operator::ALL
+            .iter()
+            .flat_map(|&op| {
+                let pattern = format!(" {op} ");
+                query.match_indices(&pattern).map(|(i, s)| (i, s.len(), op)).collect::<Vec<_>>()
+            })
+            .collect();
+        splits.sort_by_key(|&(i, _, _)| std::cmp::Reverse(i));
+        for (i, len, op) in splits {
+            let (path, literal) = (&query[..i], &query[i + len..]);
+            if let (Ok((path, length)), Ok(value)) = (Self::parse_path(path), serde_json::from_str(literal.trim())) {
+                let comparison = match (op, value) {
+                    (Operator::Match, Value::String(r)) => Comparison::Regex(Regex::new(&r).map_err(|e| invalid(&e))?),
+                    (Operator::Match, _) => Err(invalid(&"regex should be string"))?,
+                    (op, value) => Comparison::Value(op, value),
+                };
+                return Ok(ParsedQuery { path, length, comparison: Some(comparison) });
+            }
+        }
+        let (path, length) = Self::parse_path(query).map_err(|e| invalid(&e))?;
+        Ok(ParsedQuery { path, length, comparison: None })
+    }
+    fn parse_path(path: &str) -> Result<(JsonPath, bool), serde_json_path::ParseError> {
+        let path = path.trim();
+        match path.strip_suffix(Self::LENGTH_SUFFIX) {
+            Some(p) => Ok((JsonPath::parse(p)?, true)),
+            None => Ok((JsonPath::parse(path)?, false)),
+        }
+    }
+
+    pub fn evaluate(&self, value: &Value) -> Result<(), EvaluateError> {
+        let ParsedQuery { path, length, comparison } = self.parsed();
+        let nodes = path.query(value).all();
+        if nodes.is_empty() {
+            return Err(EvaluateError::custom(format!("query `{self}` found no value")));
+        }
+        let Some(comparison) = comparison else {
+            return Ok(());
+        };
+        for node in nodes {
+            let actual = if *length { Self::length(node).map(Value::from) } else { Some(node.clone()) };
+            let satisfied = match (actual.as_ref(), comparison) {
+                (Some(Value::String(s)), Comparison::Regex(regex)) => regex.is_match(s),
+                (Some(v), Comparison::Regex(regex)) => regex.is_match(&v.to_string()),
+                (Some(v), Comparison::Value(op, expected)) => op.compare(v, expected),
+                (None, _) => false,
+            };
+            if !satisfied {
+                let actual = actual.map_or_else(|| format!("no length of {node}"), |v| v.to_string());
+                return Err(EvaluateError::custom(format!("query `{self}` is not satisfied: {actual}")));
+            }
+        }
+        Ok(())
+    }
+    fn length(value: &Value) -> Option<usize> {
+        match value {
+            Value::Array(a) => Some(a.len()),
+            Value::Object(o) => Some(o.len()),
+            Value::String(s) => Some(s.chars().count()),
+            _ => None,
+        }
+    }
+}
+impl Display for Query {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.raw_query().fmt(f)
+    }
+}
+impl Operator {
+    pub const ALL: [Self; 7] = [Self::Eq, Self::Ne, Self::Gt, Self::Ge, Self::Lt, Self::Le, Self::Match];
+
+    /// Numbers and strings are ordered, other values are only compared by equality.
+    pub fn compare(&self, actual: &Value, expected: &Value) -> bool {
+        let ordering = match (actual, expected) {
+            (Value::Number(a), Value::Number(e)) => a.as_f64().zip(e.as_f64()).and_then(|(a, e)| a.partial_cmp(&e)),
+            (Value::String(a), Value::String(e)) => Some(a.cmp(e)),
+            (a, e) => (a == e).then_some(Ordering::Equal),
+        };
+        match self {
+            Self::Eq => ordering == Some(Ordering::Equal),
+            Self::Ne => ordering != Some(Ordering::Equal),
+            Self::Gt => ordering == Some(Ordering::Greater),
+            Self::Ge => matches!(ordering, Some(Ordering::Greater | Ordering::Equal)),
+            Self::Lt => ordering == Some(Ordering::Less),
+            Self::Le => matches!(ordering, Some(Ordering::Less | Ordering::Equal)),
+            Self::Match => false,
+        }
+    }
+}
+impl Display for Operator {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Eq => write!(f, "=="),
+            Self::Ne => write!(f, "!="),
+            Self::Gt => write!(f, ">"),
+            Self::Ge => write!(f, ">="),
+            Self::Lt => write!(f, "<"),
+            Self::Le => write!(f, "<="),
+            Self::Match => write!(f, "=~"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_query_evaluate() {
+        let value = json!({
+            "items": [{"id": 1, "price": "10.5"}, {"id": 2, "price": "3"}],
+            "user": {"name": "John Doe", "age": 30},
+        });
+        let satisfied = [
+            "$.items",
+            "$.items.length() > 0",
+            "$.items.length() == 2",
+            r#"$.user.name == "John Doe""#,
+            r#"$.user.name.length() >= 8"#,
+            "$.user.age >= 30",
+            "$.user.age != 31",
+            r#"$.items[*].price =~ "^[0-9]+(\\.[0-9]+)?$""#,
+            "$.items[?@.id == 2].price == \"3\"",
+            "$.items[?@.id == 2]",
+        ];
+        for q in satisfied {
+            assert!(Query::new(q).unwrap().evaluate(&value).is_ok(), "{q}");
+        }
+
+        let unsatisfied = [
+            ("$.missing", "query `$.missing` found no value"),
+            ("$.items.length() > 2", "query `$.items.length() > 2` is not satisfied: 2"),
+            (r#"$.user.name == "Jane""#, r#"query `$.user.name == "Jane"` is not satisfied: "John Doe""#),
+            ("$.user.age.length() > 0", "query `$.user.age.length() > 0` is not satisfied: no length of 30"),
+            ("$.items[*].id < 2", "query `$.items[*].id < 2` is not satisfied: 2"),
+        ];
+        for (q, m) in unsatisfied {
+            assert_eq!(Query::new(q).unwrap().evaluate(&value).unwrap_err().to_string(), m);
+        }
+    }
+
+    #[test]
+    fn test_invalid_query_fails_to_deserialize() {
+        let invalid = [
+            ("user.name", "invalid query `user.name`: at position 0, parser error"),
+            (r#"$.name =~ "(""#, "invalid query `$.name =~ \"(\"`: regex parse error"),
+            ("$.name =~ 1", "invalid query `$.name =~ 1`: regex should be string"),
+        ];
+        for (q, m) in invalid {
+            let err = serde_json::from_value::<QueryEvaluator>(json!([q])).unwrap_err();
+            assert!(err.to_string().starts_with(m), "{err}");
+        }
+    }
+
+    #[test]
+    fn test_query_evaluator_reports_all() {
+        let evaluator: QueryEvaluator = serde_json::from_value(json!(["$.a == 1", "$.b == 2", "$.c"])).unwrap();
+        let mut msg = Messages::new();
+        assert!(evaluator.evaluate_shot(&mut msg, &json!({"a": 1, "b": 3})).is_err());
+        assert_eq!(msg.len(), 2);
+    }
+}
@@ -0,0 +1,172 @@
+use std::{
+    path::PathBuf,
+    sync::{Arc, OnceLock},
+};
+
+use jsonschema::Validator;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::{
+    error::EvaluateError,
+    evaluator::evaluate::{Evaluator, Failure, Messages},
+    shot::job::BasePath,
+};
+
+/// Validation of JSON responses by JSON Schema, it works as a contract check even with a single destination.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(from = "SchemaSource", into = "SchemaSource")]
+pub struct SchemaEvaluator {
+    source: SchemaSource,
+    /// compiled by the first validation, `None` if the schema is invalid and it has been reported
+    validator: Arc<OnceLock<Option<Validator>>>,
+}
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+pub enum SchemaSource {
+    /// JSON or YAML file of the schema, relative to the base path of the job
+    File(PathBuf),
+    Inline(Value),
+}
+impl From<SchemaSource> for SchemaEvaluator {
+    fn from(source: SchemaSource) -> Self {
+        Self { source, validator: Default::default() }
+    }
+}
+impl From<SchemaEvaluator> for SchemaSource {
+    fn from(evaluator: SchemaEvaluator) -> Self {
+        evaluator.source
+    }
+}
+impl PartialEq for SchemaEvaluator {
+    fn eq(&self, other: &Self) -> bool {
+        self.source == other.source
+    }
+}
+impl Eq for SchemaEvaluator {}
+impl SchemaEvaluator {
+    /// The base path is the same during a job, so the compiled validator is shared with the unresolved evaluator,
+    /// and the schema is compiled only once even if the profile is resolved for each testcase.
+    pub fn with_base_path(self, base_path: &Option<BasePath>) -> Self {
+        match (self.source, base_path) {
+            (SchemaSource::File(path), Some(base_path)) => {
+                Self { source: SchemaSource::File(base_path.resolve(&path)), validator: self.validator }
+            }
+            (source, _) => Self { source, validator: self.validator },
+        }
+    }
+
+    pub fn schema(&self) -> Result<Value, EvaluateError> {
+        match &self.source {
+            SchemaSource::File(path) => {
+                let file = std::fs::File::open(path)
+                    .map_err(|e| EvaluateError::custom(format!("failed to open schema {}: {e}", path.display())))?;
+                Self::read_schema(file)
+                    .map_err(|e| EvaluateError::custom(format!("invalid schema {}: {e}", path.display())))
+            }
+            SchemaSource::Inline(schema) => Ok(schema.clone()),
+        }
+    }
+    #[cfg(feature = "yaml")]
+    fn read_schema(file: std::fs::File) -> Result<Value, serde_yaml::Error> {
+        serde_yaml::from_reader(file) // JSON is also valid YAML
+    }
+    #[cfg(not(feature = "yaml"))]
+    fn read_schema(file: std::fs::File) -> Result<Value, serde_json::Error> {
+        serde_json::from_reader(file)
+    }
+    pub fn compile(&self) -> Result<Validator, EvaluateError> {
+        jsonschema::validator_for(&self.schema()?).map_err(|e| EvaluateError::custom(format!("invalid schema: {e}")))
+    }
+
+    /// Each violation is reported as a separate message with its instance path.
+    /// The schema is compiled only once, and an invalid schema is reported only by the first validation.
+    pub fn validate(&self, msg: &mut Messages<EvaluateError>, res: &Value) -> Result<(), Failure> {
+        let validator = self.validator.get_or_init(|| self.compile().map_err(|e| msg.error(e)).ok());
+        let Some(validator) = validator else {
+            return Err(Failure::default());
+        };
+        let violations: Vec<_> = validator
+            .iter_errors(res)
+            .map(|e| {
+                let path = e.instance_path().to_string();
+                let path = if path.is_empty() { "(root)".to_string() } else { path };
+                EvaluateError::custom(format!("schema violation at {path}: {e}"))
+            })
+            .collect();
+        if violations.is_empty() {
+            Ok(())
+        } else {
+            violations.into_iter().for_each(|e| _ = msg.error(e));
+            Err(Failure::default())
+        }
+    }
+}
+impl Evaluator<Value> for SchemaEvaluator {
+    type Message = EvaluateError;
+    fn evaluate_shot(&self, msg: &mut Messages<Self::Message>, res: &Value) -> Result<(), Failure> {
+        self.validate(msg, res)
+    }
+    fn evaluate_compare(&self, msg: &mut Messages<Self::Message>, res1: &Value, res2: &Value) -> Result<(), Failure> {
+        self.validate(msg, res1)?;
+        self.validate(msg, res2)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_validate_inline() {
+        let evaluator = SchemaEvaluator::from(SchemaSource::Inline(json!({
+            "type": "object",
+            "required": ["id", "items"],
+            "properties": {
+                "id": {"type": "integer"},
+                "items": {"type": "array", "items": {"type": "string"}},
+            },
+        })));
+
+        let mut msg = Messages::new();
+        assert!(evaluator.evaluate_shot(&mut msg, &json!({"id": 1, "items": ["a", "b"]})).is_ok());
+        assert!(msg.is_empty());
+
+        assert!(evaluator.evaluate_shot(&mut msg, &json!({"id": "1", "items": ["a", 2]})).is_err());
+        let expected = [
+            r#"schema violation at /id: "1" is not of type "integer""#,
+            r#"schema violation at /items/1: 2 is not of type "string""#,
+        ];
+        assert_eq!(msg.to_string(), expected.map(|m| format!("{m}\n")).concat());
+    }
+
+    #[test]
+    fn test_file_relative_to_base_path() {
+        let dir = std::env::temp_dir().join(format!("relentless-schema-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("schema.json"), r#"{"type": "object", "required": ["id"]}"#).unwrap();
+
+        let evaluator: SchemaEvaluator = serde_json::from_value(json!({"file": "schema.json"})).unwrap();
+        let base_path = Some(dir.to_string_lossy().parse().unwrap());
+        let (evaluator, other) = (evaluator.clone().with_base_path(&base_path), evaluator.with_base_path(&base_path));
+        let mut msg = Messages::new();
+        assert!(evaluator.evaluate_shot(&mut msg, &json!({"id": 1})).is_ok());
+        assert!(other.validator.get().is_some()); // compiled once for all resolved evaluators
+        assert!(evaluator.evaluate_shot(&mut msg, &json!({})).is_err());
+        assert_eq!(msg.to_string(), "schema violation at (root): \"id\" is a required property\n");
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_invalid_schema_reported_once() {
+        let evaluator: SchemaEvaluator = serde_json::from_value(json!({"file": "not-found.json"})).unwrap();
+        let mut msg = Messages::new();
+        assert!(evaluator.evaluate_shot(&mut msg, &json!({})).is_err());
+        assert!(evaluator.clone().evaluate_compare(&mut msg, &json!({}), &json!({})).is_err());
+        assert_eq!(msg.len(), 1);
+        assert!(msg.to_string().starts_with("failed to open schema not-found.json: "));
+    }
+}
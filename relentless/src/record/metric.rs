@@ -1,30 +1,50 @@
 use std::{
-    future::Future,
-    pin::Pin,
     sync::{Arc, Mutex},
-    task::{Context, Poll},
     time::{Duration, Instant, SystemTime},
 };
 
 use bytesize::ByteSize;
-use pin_project::pin_project;
-use semigroup::{op::HdrHistogram, Monoid, OptionMonoid, Semigroup};
-use tower::{Layer, Service};
+use semigroup::{op::HdrHistogram, OptionMonoid, Semigroup};
 
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct Metric {
-    bytes: u64,
+    bytes: BodySize,
     timestamp: SystemTime,
     duration: (Instant, Instant),
 }
 impl Metric {
-    pub fn new(bytes: u64, timestamp: SystemTime, duration: (Instant, Instant)) -> Self {
+    pub fn new(bytes: BodySize, timestamp: SystemTime, duration: (Instant, Instant)) -> Self {
         Self { bytes, timestamp, duration }
     }
     pub fn into_agg(self) -> MetricAgg {
         self.into()
     }
 }
+/// Sizes of the request and response bodies in bytes, `None` if unknown such as a streaming body without length.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default, Hash)]
+pub struct BodySize {
+    pub request: Option<u64>,
+    pub response: Option<u64>,
+}
+/// Shared [`BodySize`] recorded while bodies are transferred, such as by a codec or after collecting a streaming body.
+#[derive(Debug, Clone, Default)]
+pub struct BodySizeRecorder(Arc<Mutex<BodySize>>);
+impl From<BodySize> for BodySizeRecorder {
+    fn from(size: BodySize) -> Self {
+        Self(Arc::new(Mutex::new(size)))
+    }
+}
+impl BodySizeRecorder {
+    pub fn record_request(&self, bytes: u64) {
+        self.0.lock().unwrap().request = Some(bytes);
+    }
+    pub fn record_response(&self, bytes: u64) {
+        self.0.lock().unwrap().response = Some(bytes);
+    }
+    pub fn body_size(&self) -> BodySize {
+        *self.0.lock().unwrap()
+    }
+}
 
 #[derive(Debug, Clone, PartialEq, Semigroup)]
 struct MetricAggInner {
@@ -34,7 +54,8 @@ struct MetricAggInner {
     start: Instant,
     #[semigroup(with = "semigroup::op::Max")]
     end: Instant,
-    bytes: HdrHistogram<u64>,
+    request_bytes: HdrHistogram<u64>,
+    response_bytes: HdrHistogram<u64>,
     latency: HdrHistogram<u64>,
 }
 #[derive(Debug, Clone, PartialEq, Semigroup)]
@@ -43,9 +64,10 @@ pub struct MetricAgg(OptionMonoid<MetricAggInner>);
 impl From<Metric> for MetricAgg {
     fn from(value: Metric) -> Self {
         let (start, end) = value.duration;
-        let bytes = value.bytes.into();
+        let (request_bytes, response_bytes) = (value.bytes.request.into_iter(), value.bytes.response.into_iter());
+        let (request_bytes, response_bytes) = (request_bytes.collect(), response_bytes.collect());
         let latency = ((end - start).as_millis() as u64).into();
-        Self(MetricAggInner { times: 1, start, end, bytes, latency }.into())
+        Self(MetricAggInner { times: 1, start, end, request_bytes, response_bytes, latency }.into())
     }
 }
 impl MetricAgg {
@@ -58,12 +80,15 @@ impl MetricAgg {
     pub fn rps(&self) -> f64 {
         self.times() as f64 / self.duration().as_secs_f64()
     }
-    pub fn approx_bytes_quantile(&self, quantile: f64) -> ByteSize {
-        self.0
-            .as_ref()
-            .map(|agg| agg.bytes.histogram().value_at_quantile(quantile))
-            .map(ByteSize::b)
-            .unwrap_or_default()
+    /// `None` if no size of requests is recorded.
+    pub fn approx_request_bytes_quantile(&self, quantile: f64) -> Option<ByteSize> {
+        let histogram = self.0.as_ref().map(|agg| agg.request_bytes.histogram())?;
+        (!histogram.is_empty()).then(|| ByteSize::b(histogram.value_at_quantile(quantile)))
+    }
+    /// `None` if no size of responses is recorded.
+    pub fn approx_response_bytes_quantile(&self, quantile: f64) -> Option<ByteSize> {
+        let histogram = self.0.as_ref().map(|agg| agg.response_bytes.histogram())?;
+        (!histogram.is_empty()).then(|| ByteSize::b(histogram.value_at_quantile(quantile)))
     }
     pub fn approx_latency_quantile(&self, quantile: f64) -> Duration {
         self.0
@@ -72,122 +97,123 @@ impl MetricAgg {
             .map(Duration::from_millis)
             .unwrap_or_default()
     }
-}
-
-#[derive(Debug, Clone)]
-pub struct MeasureLayer {
-    agg: Arc<Mutex<MetricAgg>>,
-}
-impl MeasureLayer {
-    #[allow(clippy::new_without_default)] // TODO
-    pub fn new() -> Self {
-        let agg = Arc::new(Mutex::new(MetricAgg::identity()));
-        Self { agg }
-    }
-    pub fn aggregated(&self) -> MetricAgg {
-        self.agg.lock().unwrap().clone()
-    }
-}
-impl<S> Layer<S> for MeasureLayer {
-    type Service = MeasureService<S>;
 
-    fn layer(&self, inner: S) -> Self::Service {
-        let agg = self.agg.clone();
-        MeasureService { inner, agg }
-    }
-}
-
-#[derive(Debug, Clone)]
-pub struct MeasureService<S> {
-    inner: S,
-    agg: Arc<Mutex<MetricAgg>>,
-}
-
-impl<S, Req> Service<Req> for MeasureService<S>
-where
-    S: Service<Req>,
-{
-    type Response = S::Response;
-    type Error = S::Error;
-    type Future = MeasureFuture<S::Future>;
-
-    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
-        self.inner.poll_ready(cx)
+    /// One-sided p-value of Mann-Whitney U test that latencies are greater than the ones of `baseline`.
+    /// It is computed by normal approximation with tie correction, `None` if there is no variance such as no requests.
+    pub fn latency_greater_p_value(&self, baseline: &Self) -> Option<f64> {
+        let (counts, base_counts) = (self.latency_counts(), baseline.latency_counts());
+        let (n1, n2) = (base_counts.values().sum::<u64>() as f64, counts.values().sum::<u64>() as f64);
+        let values: std::collections::BTreeSet<_> = counts.keys().chain(base_counts.keys()).collect();
+        let (mut u, mut below, mut ties) = (0.0, 0.0, 0.0);
+        for v in values {
+            let (c1, c2) = (*base_counts.get(v).unwrap_or(&0) as f64, *counts.get(v).unwrap_or(&0) as f64);
+            u += c2 * (below + c1 / 2.0);
+            below += c1;
+            ties += (c1 + c2).powi(3) - (c1 + c2);
+        }
+        let n = n1 + n2;
+        let variance = n1 * n2 / 12.0 * ((n + 1.0) - ties / (n * (n - 1.0)));
+        if variance.is_nan() || variance <= 0.0 {
+            return None;
+        }
+        let z = (u - n1 * n2 / 2.0) / variance.sqrt();
+        Some(erfc(z / std::f64::consts::SQRT_2) / 2.0)
     }
-
-    fn call(&mut self, req: Req) -> Self::Future {
-        MeasureFuture::new(self.inner.call(req), self.agg.clone())
+    fn latency_counts(&self) -> std::collections::BTreeMap<u64, u64> {
+        self.0
+            .as_ref()
+            .map(|agg| {
+                let histogram = agg.latency.histogram();
+                histogram.iter_recorded().map(|v| (v.value_iterated_to(), v.count_at_value())).collect()
+            })
+            .unwrap_or_default()
     }
 }
 
-#[pin_project]
-pub struct MeasureFuture<F> {
-    #[pin]
-    fut: F,
-    start: Option<(SystemTime, Instant)>,
-    end: Option<()>,
-    agg: Arc<Mutex<MetricAgg>>,
-}
-impl<F> MeasureFuture<F> {
-    pub fn new(fut: F, agg: Arc<Mutex<MetricAgg>>) -> Self {
-        Self { fut, start: None, end: Some(()), agg }
+/// Latency percentiles, throughput and size percentiles of recorded bodies,
+/// such as `p50 12ms, p99 30ms, 83.1 rps, request p50 120 B p99 130 B, response p50 1.2 KiB p99 1.5 KiB`.
+impl std::fmt::Display for MetricAgg {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let (p50, p99) = (self.approx_latency_quantile(0.5), self.approx_latency_quantile(0.99));
+        write!(f, "p50 {p50:?}, p99 {p99:?}, {:.1} rps", self.rps())?;
+        let request = self.approx_request_bytes_quantile(0.5).zip(self.approx_request_bytes_quantile(0.99));
+        if let Some((p50, p99)) = request {
+            write!(f, ", request p50 {p50} p99 {p99}")?;
+        }
+        let response = self.approx_response_bytes_quantile(0.5).zip(self.approx_response_bytes_quantile(0.99));
+        if let Some((p50, p99)) = response {
+            write!(f, ", response p50 {p50} p99 {p99}")?;
+        }
+        Ok(())
     }
 }
 
-impl<F, T, E> Future for MeasureFuture<F>
-where
-    F: Future<Output = Result<T, E>>,
-{
-    type Output = Result<T, E>;
-
-    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
-        let this = self.project();
-        if this.start.is_none() {
-            *this.start = Some((SystemTime::now(), Instant::now()));
-        }
-
-        this.fut.poll(cx).map(|o| {
-            let Some((timestamp, start)) = this.start.take() else { unreachable!() };
-            let end = Instant::now();
-            let () = this.end.take().expect("poll after ready");
-
-            let metric = Metric::new(0, timestamp, (start, end)).into_agg();
-            let mut agg = this.agg.lock().unwrap();
-            agg.semigroup_assign(metric);
-
-            o
-        })
+/// Complementary error function, Chebyshev approximation of Numerical Recipes with fractional error less than 1.2e-7.
+fn erfc(x: f64) -> f64 {
+    let z = x.abs();
+    let t = 1.0 / (1.0 + 0.5 * z);
+    let coefficients = [
+        -1.26551223,
+        1.00002368,
+        0.37409196,
+        0.09678418,
+        -0.18628806,
+        0.27886807,
+        -1.13520398,
+        1.48851587,
+        -0.82215223,
+        0.17087277,
+    ];
+    let poly = coefficients.iter().rev().fold(0.0, |acc, c| c + t * acc);
+    let r = t * (-z * z + poly).exp();
+    if x >= 0.0 {
+        r
+    } else {
+        2.0 - r
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use std::convert::Infallible;
-
-    use futures::StreamExt;
-    use tower::{ServiceBuilder, ServiceExt};
+    use semigroup::Monoid;
 
     use super::*;
 
-    #[tokio::test]
-    async fn test_measure_layer() {
-        let measure = MeasureLayer::new();
-        let svc = tower::service_fn(|_| async {
-            tokio::time::sleep(Duration::from_secs(1)).await;
-            Ok::<_, Infallible>(())
-        });
-
-        let service = ServiceBuilder::new().layer(measure.clone()).service(svc);
-        let stream = futures::stream::iter(0..180)
-            .map(|_| async { service.clone().oneshot(()).await.unwrap() })
-            .buffer_unordered(180);
-
-        let count = stream.count().await;
-
-        // TODO depend on runtime ?
-        let agg = measure.aggregated();
-        assert_eq!(agg.times(), count as u64);
-        assert!((175.0..180.0).contains(&agg.rps()));
-        assert!((Duration::from_millis(1000)..Duration::from_millis(1100)).contains(&agg.approx_latency_quantile(0.99)))
+    #[test]
+    fn test_latency_greater_p_value() {
+        let agg = |latencies: &mut dyn Iterator<Item = u64>| {
+            let start = Instant::now();
+            latencies
+                .map(|ms| (BodySize::default(), SystemTime::now(), (start, start + Duration::from_millis(ms))))
+                .map(|(bytes, timestamp, duration)| Metric::new(bytes, timestamp, duration).into_agg())
+                .fold(MetricAgg::identity(), Semigroup::semigroup)
+        };
+        let (baseline, slower, same) = (agg(&mut (10..20)), agg(&mut (30..40)), agg(&mut (10..20).rev()));
+
+        assert!(slower.latency_greater_p_value(&baseline).unwrap() < 0.001);
+        assert!(baseline.latency_greater_p_value(&slower).unwrap() > 0.999);
+        assert!((same.latency_greater_p_value(&baseline).unwrap() - 0.5).abs() < 1e-6);
+        // small samples are not significant even if all of them are slower
+        assert!(agg(&mut [30].into_iter()).latency_greater_p_value(&agg(&mut [10].into_iter())).unwrap() > 0.05);
+        assert_eq!(MetricAgg::identity().latency_greater_p_value(&baseline), None);
+    }
+
+    #[test]
+    fn test_body_size() {
+        let start = Instant::now();
+        let metric = |request, response| {
+            let bytes = BodySize { request, response };
+            Metric::new(bytes, SystemTime::now(), (start, start + Duration::from_millis(10))).into_agg()
+        };
+        let agg = metric(None, Some(1000)).semigroup(metric(None, Some(3000))).semigroup(metric(None, None));
+        assert_eq!(agg.approx_request_bytes_quantile(0.5), None);
+        assert_eq!(agg.approx_response_bytes_quantile(0.5), Some(ByteSize::b(1000)));
+        assert_eq!(agg.approx_response_bytes_quantile(1.0), Some(ByteSize::b(3001)));
+        assert!(!agg.to_string().contains("request"));
+
+        let agg = agg.semigroup(metric(Some(10), None));
+        assert_eq!(agg.approx_request_bytes_quantile(0.5), Some(ByteSize::b(10)));
+        let expected = "p50 10ms, p99 10ms, 400.0 rps, request p50 10 B p99 10 B, response p50 1000 B p99 2.9 KiB";
+        assert_eq!(agg.to_string(), expected);
     }
 }
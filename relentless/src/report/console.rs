@@ -3,12 +3,13 @@ use std::fmt::{Display, Write as _};
 use console::{Emoji, Style, StyledObject};
 
 use crate::{
-    evaluator::evaluate::{Message, MessageKind},
+    evaluator::evaluate::{Message, MessageExt, MessageKind},
     report::{ReportSpec, ReportWriter, Reporter},
     shot::{
         contract::{Assessment, Evaluated},
         job::JobReport,
         profile::Repeat,
+        scheduler::Stage,
         suite::SuiteReport,
         testcase::CaseReport,
     },
@@ -21,13 +22,22 @@ impl<'a> Console<'a> {
     pub const SUITE_NAME_EMOJI: Emoji<'static, 'static> = Emoji("🚀", "");
     pub const SUITE_DESTINATION_EMOJI: Emoji<'static, 'static> = Emoji("🌐", ":");
     pub const SUITE_OVERWRITE_DESTINATION_EMOJI: Emoji<'static, 'static> = Emoji("👉", "->");
+    pub const SUITE_SETUP_EMOJI: Emoji<'static, 'static> = Emoji("🔧", "");
+    pub const SUITE_TEARDOWN_EMOJI: Emoji<'static, 'static> = Emoji("🧹", "");
+    pub const SUITE_USER_EMOJI: Emoji<'static, 'static> = Emoji("👤", ":");
+    pub const SUITE_METRIC_EMOJI: Emoji<'static, 'static> = Emoji("⏱️", ":");
 
     pub const CASE_PASS_EMOJI: Emoji<'static, 'static> = Emoji("✅", "PASS");
     pub const CASE_FAIL_EMOJI: Emoji<'static, 'static> = Emoji("❌", "FAIL");
     pub const CASE_REPEAT_EMOJI: Emoji<'static, 'static> = Emoji("🔁", "");
+    pub const CASE_RETRY_EMOJI: Emoji<'static, 'static> = Emoji("🔄", "retry");
     pub const CASE_DESCRIPTION_EMOJI: Emoji<'static, 'static> = Emoji("📝", "");
     pub const CASE_ALLOW_EMOJI: Emoji<'static, 'static> = Emoji("👀", "");
     pub const CASE_MESSAGE_EMOJI: Emoji<'static, 'static> = Emoji("💬", "");
+    pub const CASE_STAGE_EMOJI: Emoji<'static, 'static> = Emoji("📈", "");
+    pub const CASE_REQUEST_EMOJI: Emoji<'static, 'static> = Emoji("📤", "request");
+    pub const CASE_METRIC_EMOJI: Emoji<'static, 'static> = Emoji("⏱️", "metric");
+    pub const CASE_SKIP_EMOJI: Emoji<'static, 'static> = Emoji("⏭️", "SKIP");
 
     pub const SUMMARY_EMOJI: Emoji<'static, 'static> = Emoji("💥", "");
 
@@ -56,8 +66,41 @@ impl<'a> Console<'a> {
     pub fn styled_message<'b, T>(&self, msg: &'b Message<T>) -> StyledObject<&'b T> {
         self.message_style(&msg.kind).apply_to(&msg.message)
     }
+    /// Lines of [`crate::evaluator::plaintext::TextDiff`] are colored as added or removed, the other lines are in the style of the message.
+    pub fn write_message<W: std::io::Write, T: Display + MessageExt>(
+        &self,
+        writer: &mut ReportWriter<W>,
+        msg: &Message<T>,
+    ) -> std::fmt::Result {
+        if msg.message.text_diff().is_none() {
+            return writeln!(writer, "{}", self.styled_message(msg));
+        }
+        msg.message.to_string().lines().try_for_each(|line| {
+            let style = match line {
+                l if l.starts_with("@@") => Style::new().cyan(),
+                l if l.starts_with('+') => Style::new().green(),
+                l if l.starts_with('-') => Style::new().red(),
+                _ => self.message_style(&msg.kind),
+            };
+            writeln!(writer, "{}", style.apply_to(line))
+        })
+    }
+
+    pub fn write_hooks<W: std::io::Write, Q, P, M: Display + MessageExt>(
+        &self,
+        writer: &mut ReportWriter<W>,
+        emoji: Emoji<'static, 'static>,
+        name: &str,
+        hooks: &[CaseReport<Q, P, M>],
+    ) -> std::fmt::Result {
+        if hooks.is_empty() {
+            return Ok(());
+        }
+        writeln!(writer, "{emoji} {name}")?;
+        writer.scope(|w| hooks.iter().try_for_each(|c| self.write_report(w, c)))
+    }
 }
-impl<C, Q, P, M: Display> Reporter<&JobReport<'_, C, Q, P, M>> for Console<'_> {
+impl<C, Q, P, M: Display + MessageExt> Reporter<&JobReport<'_, C, Q, P, M>> for Console<'_> {
     type Error = std::fmt::Error;
     fn write_report<W: std::io::Write>(
         &self,
@@ -79,7 +122,7 @@ impl<C, Q, P, M: Display> Reporter<&JobReport<'_, C, Q, P, M>> for Console<'_> {
         Ok(())
     }
 }
-impl<C, Q, P, M: Display> Reporter<&SuiteReport<'_, C, Q, P, M>> for Console<'_> {
+impl<C, Q, P, M: Display + MessageExt> Reporter<&SuiteReport<'_, C, Q, P, M>> for Console<'_> {
     type Error = std::fmt::Error;
     fn write_report<W: std::io::Write>(
         &self,
@@ -89,19 +132,38 @@ impl<C, Q, P, M: Display> Reporter<&SuiteReport<'_, C, Q, P, M>> for Console<'_>
         writeln!(writer, "{} {} {}", Self::SUITE_NAME_EMOJI, report.suite.name, Self::SUITE_NAME_EMOJI)?;
         writer.scope(|w| {
             let (first, last) = (report.destinations.first(), report.destinations.last());
-            report.destinations.combine_rev_clone().iter().try_fold((), |(), (name, dest)| {
-                write!(w, "{name}{} ", Self::SUITE_DESTINATION_EMOJI)?;
-                if let (Some(base), Some(overwrite)) = (first.get(name), last.get(name)) {
-                    writeln!(w, "{base} {} {overwrite}", Self::SUITE_OVERWRITE_DESTINATION_EMOJI)
-                } else {
-                    writeln!(w, "{dest}")
-                }
-            })
+            report.destinations.combine_rev_clone().ordered(&report.suite.destinations, None).iter().try_fold(
+                (),
+                |(), (name, dest)| {
+                    write!(w, "{name}{} ", Self::SUITE_DESTINATION_EMOJI)?;
+                    if let (Some(base), Some(overwrite)) = (first.get(name), last.get(name)) {
+                        writeln!(w, "{base} {} {overwrite}", Self::SUITE_OVERWRITE_DESTINATION_EMOJI)
+                    } else {
+                        writeln!(w, "{dest}")
+                    }
+                },
+            )?;
+            report.users.iter().enumerate().try_for_each(|(i, u)| {
+                let (assessment, Evaluated { allowed, times, .. }) = (u.evaluated.assess(), &u.evaluated);
+                let evaluated = self.styled(&assessment, format!("{allowed}/{times}"));
+                writeln!(w, "user {i}{} {} iterations {evaluated}", Self::SUITE_USER_EMOJI, u.iterations)
+            })?;
+            report.metrics.iter().try_for_each(|(name, metric)| {
+                writeln!(w, "{name}{} {}", Self::SUITE_METRIC_EMOJI, Style::new().dim().apply_to(metric))
+            })?;
+            let (mut lines, and_more) = report.messages.display_lines();
+            lines.try_for_each(|l| {
+                write!(w, "{} ", Self::CASE_MESSAGE_EMOJI)?;
+                w.scope_n(3, |w| self.write_message(w, l))
+            })?;
+            and_more.iter().try_for_each(|m| writeln!(w, "... and {m} more"))
         })?;
-        report.cases.iter().try_fold((), |(), c| self.write_report(writer, c))
+        self.write_hooks(writer, Self::SUITE_SETUP_EMOJI, "setup", &report.setup)?;
+        report.cases.iter().try_fold((), |(), c| self.write_report(writer, c))?;
+        self.write_hooks(writer, Self::SUITE_TEARDOWN_EMOJI, "teardown", &report.teardown)
     }
 }
-impl<Q, P, M: Display> Reporter<&CaseReport<'_, Q, P, M>> for Console<'_> {
+impl<Q, P, M: Display + MessageExt> Reporter<&CaseReport<'_, Q, P, M>> for Console<'_> {
     type Error = std::fmt::Error;
     fn write_report<W: std::io::Write>(
         &self,
@@ -109,10 +171,18 @@ impl<Q, P, M: Display> Reporter<&CaseReport<'_, Q, P, M>> for Console<'_> {
         report: &CaseReport<Q, P, M>,
     ) -> Result<(), Self::Error> {
         let assessment = report.evaluated.assess();
-        let Evaluated { allowed, .. } = &report.evaluated;
+        let Evaluated { allowed, times, retries, .. } = &report.evaluated;
         if self.spec.ng_only && assessment != Assessment::Bad {
             return Ok(());
         }
+        if report.skipped {
+            let skipped = Style::new().dim();
+            write!(writer, "{} {}", Self::CASE_SKIP_EMOJI, skipped.apply_to(&report.case.target))?;
+            if let Some(description) = &report.case.description {
+                write!(writer, " {} {description}", Self::CASE_DESCRIPTION_EMOJI)?;
+            }
+            return writeln!(writer, " {}", skipped.apply_to("skipped"));
+        }
         let l1 = {
             let icon = match assessment {
                 Assessment::Good => self.styled(&assessment, Self::CASE_PASS_EMOJI),
@@ -120,8 +190,11 @@ impl<Q, P, M: Display> Reporter<&CaseReport<'_, Q, P, M>> for Console<'_> {
                 Assessment::Bad => self.styled(&assessment, Self::CASE_FAIL_EMOJI),
             };
             write!(writer, "{icon} {}", self.styled(&assessment, &report.case.target))?;
-            if let Repeat(Some(repeat)) = &report.case.profile.repeat {
-                write!(writer, " {}{allowed}/{repeat}", Self::CASE_REPEAT_EMOJI)?;
+            if matches!(report.case.profile.repeat, Repeat(Some(_))) || *times > 1 {
+                write!(writer, " {}{allowed}/{times}", Self::CASE_REPEAT_EMOJI)?;
+            }
+            if *retries > 0 {
+                write!(writer, " {} {retries} retries", Self::CASE_RETRY_EMOJI)?;
             }
             if let Some(description) = &report.case.description {
                 write!(writer, " {} {description}", Self::CASE_DESCRIPTION_EMOJI)?;
@@ -134,11 +207,26 @@ impl<Q, P, M: Display> Reporter<&CaseReport<'_, Q, P, M>> for Console<'_> {
                 let (mut lines, and_more) = report.messages.display_lines();
                 lines.try_for_each(|l| {
                     write!(w, "{} ", Self::CASE_MESSAGE_EMOJI)?;
-                    w.scope_n(3, |w| writeln!(w, "{}", self.styled_message(l)))
+                    w.scope_n(3, |w| self.write_message(w, l))
                 })?;
                 and_more.iter().try_for_each(|m| writeln!(w, "... and {m} more"))
             };
-            l2
+            l2?;
+            report.requests.iter().try_for_each(|(name, request)| {
+                writeln!(w, "{} {name}", Self::CASE_REQUEST_EMOJI)?;
+                w.scope_n(3, |w| request.lines().try_for_each(|l| writeln!(w, "{l}")))
+            })?;
+            report.stages.iter().enumerate().try_for_each(|(i, s)| {
+                let assessment = s.evaluated.assess();
+                let (Stage { duration, target, from }, Evaluated { allowed, times, .. }) = (&s.stage, &s.evaluated);
+                write!(w, "{} stage {i}: ", Self::CASE_STAGE_EMOJI)?;
+                from.iter().try_for_each(|from| write!(w, "{from} -> "))?;
+                write!(w, "{target} rps in {duration:?} {}", self.styled(&assessment, format!("{allowed}/{times}")))?;
+                writeln!(w, " ({})", s.metric)
+            })?;
+            report.metrics.iter().try_for_each(|(name, metric)| {
+                writeln!(w, "{} {name}: {}", Self::CASE_METRIC_EMOJI, Style::new().dim().apply_to(metric))
+            })
         })?;
         Ok(())
     }
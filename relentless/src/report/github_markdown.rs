@@ -1,8 +1,16 @@
 use std::fmt::{Display, Write as _};
 
 use crate::{
+    evaluator::evaluate::MessageExt,
     report::{ReportSpec, ReportWriter, Reporter},
-    shot::{contract::Assessment, job::JobReport, profile::Repeat, suite::SuiteReport, testcase::CaseReport},
+    shot::{
+        contract::{Assessment, Evaluated},
+        job::JobReport,
+        profile::Repeat,
+        scheduler::Stage,
+        suite::SuiteReport,
+        testcase::CaseReport,
+    },
 };
 
 pub struct GithubMarkdown<'a> {
@@ -12,20 +20,44 @@ impl<'a> GithubMarkdown<'a> {
     pub const SUITE_NAME_EMOJI: &'static str = ":rocket:";
     pub const DESTINATION_EMOJI: &'static str = ":globe_with_meridians:";
     pub const OVERWRITE_DESTINATION_EMOJI: &'static str = ":point_right:";
+    pub const SUITE_SETUP_EMOJI: &'static str = ":wrench:";
+    pub const SUITE_TEARDOWN_EMOJI: &'static str = ":broom:";
+    pub const USER_EMOJI: &'static str = ":bust_in_silhouette:";
+    pub const METRIC_EMOJI: &'static str = ":stopwatch:";
 
     pub const CASE_PASS_EMOJI: &'static str = ":white_check_mark:";
     pub const CASE_FAIL_EMOJI: &'static str = ":x:";
     pub const CASE_ALLOW_EMOJI: &'static str = ":eyes:";
     pub const CASE_REPEAT_EMOJI: &'static str = ":repeat:";
+    pub const CASE_RETRY_EMOJI: &'static str = ":arrows_counterclockwise:";
     pub const CASE_DESCRIPTION_EMOJI: &'static str = ":memo:";
     pub const CASE_MESSAGE_EMOJI: &'static str = ":speech_balloon:";
+    pub const CASE_STAGE_EMOJI: &'static str = ":chart_with_upwards_trend:";
+    pub const CASE_REQUEST_EMOJI: &'static str = ":outbox_tray:";
+    pub const CASE_SKIP_EMOJI: &'static str = ":next_track_button:";
 
     pub fn new(spec: &'a ReportSpec) -> Self {
         Self { spec }
     }
+
+    pub fn write_hooks<W: std::io::Write, Q, P, M: Display + MessageExt>(
+        &self,
+        writer: &mut ReportWriter<W>,
+        emoji: &str,
+        name: &str,
+        hooks: &[CaseReport<Q, P, M>],
+    ) -> std::fmt::Result {
+        if hooks.is_empty() {
+            return Ok(());
+        }
+        writeln!(writer, "**{emoji} {name}**")?;
+        writeln!(writer)?;
+        hooks.iter().try_for_each(|c| self.write_report(writer, c))?;
+        writeln!(writer)
+    }
 }
 
-impl<C, Q, P, M: Display> Reporter<&JobReport<'_, C, Q, P, M>> for GithubMarkdown<'_> {
+impl<C, Q, P, M: Display + MessageExt> Reporter<&JobReport<'_, C, Q, P, M>> for GithubMarkdown<'_> {
     type Error = std::fmt::Error;
 
     fn write_report<W: std::io::Write>(
@@ -49,7 +81,7 @@ impl<C, Q, P, M: Display> Reporter<&JobReport<'_, C, Q, P, M>> for GithubMarkdow
     }
 }
 
-impl<C, Q, P, M: Display> Reporter<&SuiteReport<'_, C, Q, P, M>> for GithubMarkdown<'_> {
+impl<C, Q, P, M: Display + MessageExt> Reporter<&SuiteReport<'_, C, Q, P, M>> for GithubMarkdown<'_> {
     type Error = std::fmt::Error;
 
     fn write_report<W: std::io::Write>(
@@ -59,21 +91,40 @@ impl<C, Q, P, M: Display> Reporter<&SuiteReport<'_, C, Q, P, M>> for GithubMarkd
     ) -> Result<(), Self::Error> {
         writeln!(writer, "## {} {} {}", Self::SUITE_NAME_EMOJI, report.suite.name, Self::SUITE_NAME_EMOJI)?;
         let (first, last) = (report.destinations.first(), report.destinations.last());
-        report.destinations.combine_rev_clone().iter().try_for_each(|(name, dest)| {
-            write!(writer, "- {name} {} ", Self::DESTINATION_EMOJI)?;
-            if let (Some(base), Some(overwrite)) = (first.get(name), last.get(name)) {
-                writeln!(writer, "{} {} {}", base, Self::OVERWRITE_DESTINATION_EMOJI, overwrite)
-            } else {
-                writeln!(writer, "{dest}")
-            }
+        report.destinations.combine_rev_clone().ordered(&report.suite.destinations, None).iter().try_for_each(
+            |(name, dest)| {
+                write!(writer, "- {name} {} ", Self::DESTINATION_EMOJI)?;
+                if let (Some(base), Some(overwrite)) = (first.get(name), last.get(name)) {
+                    writeln!(writer, "{} {} {}", base, Self::OVERWRITE_DESTINATION_EMOJI, overwrite)
+                } else {
+                    writeln!(writer, "{dest}")
+                }
+            },
+        )?;
+        report.users.iter().enumerate().try_for_each(|(i, u)| {
+            let Evaluated { allowed, times, .. } = &u.evaluated;
+            writeln!(writer, "- user {i} {} {} iterations {allowed}/{times}", Self::USER_EMOJI, u.iterations)
         })?;
+        report
+            .metrics
+            .iter()
+            .try_for_each(|(name, metric)| writeln!(writer, "- {name} {} {metric}", Self::METRIC_EMOJI))?;
         writeln!(writer)?;
+        if !report.messages.is_empty() {
+            writeln!(writer, "{} suite failed", Self::CASE_FAIL_EMOJI)?;
+            writeln!(writer, "```")?;
+            writeln!(writer, "{}", &report.messages)?;
+            writeln!(writer, "```")?;
+            writeln!(writer)?;
+        }
 
-        report.cases.iter().try_for_each(|c| self.write_report(writer, c))
+        self.write_hooks(writer, Self::SUITE_SETUP_EMOJI, "setup", &report.setup)?;
+        report.cases.iter().try_for_each(|c| self.write_report(writer, c))?;
+        self.write_hooks(writer, Self::SUITE_TEARDOWN_EMOJI, "teardown", &report.teardown)
     }
 }
 
-impl<Q, P, M: Display> Reporter<&CaseReport<'_, Q, P, M>> for GithubMarkdown<'_> {
+impl<Q, P, M: Display + MessageExt> Reporter<&CaseReport<'_, Q, P, M>> for GithubMarkdown<'_> {
     type Error = std::fmt::Error;
 
     fn write_report<W: std::io::Write>(
@@ -86,6 +137,13 @@ impl<Q, P, M: Display> Reporter<&CaseReport<'_, Q, P, M>> for GithubMarkdown<'_>
             return Ok(());
         }
 
+        if report.skipped {
+            write!(writer, "{} `{}` skipped ", Self::CASE_SKIP_EMOJI, report.case.target)?;
+            if let Some(description) = &report.case.description {
+                write!(writer, "{} {description}", Self::CASE_DESCRIPTION_EMOJI)?;
+            }
+            return writeln!(writer);
+        }
         let icon = match assessment {
             Assessment::Good => Self::CASE_PASS_EMOJI,
             Assessment::Acceptable | Assessment::Poor => Self::CASE_ALLOW_EMOJI,
@@ -93,8 +151,12 @@ impl<Q, P, M: Display> Reporter<&CaseReport<'_, Q, P, M>> for GithubMarkdown<'_>
         };
 
         write!(writer, "{icon} `{}` ", report.case.target)?;
-        if let Repeat(Some(repeat)) = &report.case.profile.repeat {
-            write!(writer, "{} {}/{repeat} ", Self::CASE_REPEAT_EMOJI, report.evaluated.allowed)?;
+        let Evaluated { allowed, times, retries, .. } = &report.evaluated;
+        if matches!(report.case.profile.repeat, Repeat(Some(_))) || *times > 1 {
+            write!(writer, "{} {allowed}/{times} ", Self::CASE_REPEAT_EMOJI)?;
+        }
+        if *retries > 0 {
+            write!(writer, "{} {retries} retries ", Self::CASE_RETRY_EMOJI)?;
         }
         if let Some(description) = &report.case.description {
             writeln!(writer, "{} {description}", Self::CASE_DESCRIPTION_EMOJI)?;
@@ -102,13 +164,40 @@ impl<Q, P, M: Display> Reporter<&CaseReport<'_, Q, P, M>> for GithubMarkdown<'_>
             writeln!(writer)?;
         }
 
+        writer.scope(|w| {
+            report.stages.iter().enumerate().try_for_each(|(i, s)| {
+                let (Stage { duration, target, from }, Evaluated { allowed, times, .. }) = (&s.stage, &s.evaluated);
+                write!(w, "- {} stage {i}: ", Self::CASE_STAGE_EMOJI)?;
+                from.iter().try_for_each(|from| write!(w, "{from} -> "))?;
+                write!(w, "{target} rps in {duration:?} {allowed}/{times}")?;
+                writeln!(w, " ({})", s.metric)
+            })?;
+            report
+                .metrics
+                .iter()
+                .try_for_each(|(name, metric)| writeln!(w, "- {} {name}: {metric}", Self::METRIC_EMOJI))
+        })?;
+
+        report.requests.iter().try_for_each(|(name, request)| {
+            writer.scope(|w| {
+                writeln!(w, "- {} {name}", Self::CASE_REQUEST_EMOJI)?;
+                w.scope(|w| {
+                    writeln!(w, "```")?;
+                    request.lines().try_for_each(|l| writeln!(w, "{l}"))?;
+                    writeln!(w, "```")
+                })
+            })
+        })?;
+
         if !report.messages.is_empty() {
             writer.scope(|w| {
                 writeln!(w, "<details>")?;
                 w.scope(|w| {
                     writeln!(w, "<summary> {} message was found </summary>", Self::CASE_MESSAGE_EMOJI)?;
                     writeln!(w)?;
-                    writeln!(w, "```")?;
+                    let (mut lines, _) = report.messages.display_lines();
+                    let diff = lines.any(|l| l.message.text_diff().is_some());
+                    writeln!(w, "```{}", if diff { "diff" } else { "" })?;
                     writeln!(w, "{}", &report.messages)?;
                     writeln!(w, "```")
                 })?;
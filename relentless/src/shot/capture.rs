@@ -0,0 +1,182 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "json")]
+use serde_json::Value;
+
+use crate::{
+    evaluator::evaluate::{MessageExt, Messages},
+    shot::destinations::Destinations,
+    template::Template,
+};
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+pub enum Capture {
+    /// JSON pointer of the response body or message, such as `/data/createContent/id`
+    Pointer(String),
+    /// name of the response header or metadata
+    Header(String),
+}
+
+/// Values captured from responses of each destination, they are used as variables of templates of later testcases.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Captures {
+    capture: HashMap<String, Capture>,
+    captured: Destinations<Template>,
+}
+impl Captures {
+    pub fn new(capture: HashMap<String, Capture>) -> Self {
+        Self { capture, captured: Destinations::default() }
+    }
+    pub fn is_empty(&self) -> bool {
+        self.capture.is_empty()
+    }
+    pub fn captured(&self) -> &Destinations<Template> {
+        &self.captured
+    }
+    pub fn into_captured(self) -> Destinations<Template> {
+        self.captured
+    }
+
+    /// Capture values of [`Capture::Header`], missing ones are warned.
+    pub fn capture_header<M, F>(&mut self, msg: &mut Messages<M>, destination: &str, header: F)
+    where
+        M: MessageExt,
+        F: Fn(&str) -> Option<String>,
+    {
+        let headers = self.capture.iter().filter_map(|(var, c)| match c {
+            Capture::Header(name) => Some((var, name)),
+            Capture::Pointer(_) => None,
+        });
+        for (var, name) in headers {
+            match header(name) {
+                Some(value) => self.captured.entry(destination.to_string()).or_default().insert(var, value),
+                None => msg.warn(M::custom(format!("{destination}: header `{name}` to capture `{var}` is not found"))),
+            }
+        }
+    }
+
+    /// Capture values of [`Capture::Pointer`], a string is captured without quotes and others are captured as JSON.
+    #[cfg(feature = "json")]
+    pub fn capture_json<M: MessageExt>(&mut self, msg: &mut Messages<M>, destination: &str, value: &Value) {
+        let pointers = self.capture.iter().filter_map(|(var, c)| match c {
+            Capture::Pointer(pointer) => Some((var, pointer)),
+            Capture::Header(_) => None,
+        });
+        for (var, pointer) in pointers {
+            match value.pointer(pointer) {
+                Some(Value::String(s)) => self.captured.entry(destination.to_string()).or_default().insert(var, s),
+                Some(v) => self.captured.entry(destination.to_string()).or_default().insert(var, v.to_string()),
+                None => {
+                    msg.warn(M::custom(format!("{destination}: pointer `{pointer}` to capture `{var}` is not found")))
+                }
+            }
+        }
+    }
+
+    /// Same as [`Captures::capture_json`], but the body is parsed only if there are pointers to capture.
+    #[cfg(feature = "json")]
+    pub fn capture_body<M: MessageExt>(&mut self, msg: &mut Messages<M>, destination: &str, body: &[u8]) {
+        if !self.capture.values().any(|c| matches!(c, Capture::Pointer(_))) {
+            return;
+        }
+        match serde_json::from_slice(body) {
+            Ok(value) => self.capture_json(msg, destination, &value),
+            Err(e) => msg.warn(M::custom(format!("{destination}: body to capture is not JSON: {e}"))),
+        }
+    }
+}
+
+/// Merge captured variables of each destination, the ones in `captured` overwrite existing ones of the same name.
+pub fn merge(templates: &mut Destinations<Template>, captured: Destinations<Template>) {
+    for (destination, vars) in captured {
+        templates.entry(destination).or_default().extend(vars);
+    }
+}
+
+#[cfg(all(test, feature = "json"))]
+mod tests {
+    use serde_json::json;
+
+    use crate::{
+        error::EvaluateError,
+        shot::{
+            job::{Job, JobSpec},
+            profile::Profile,
+            testcase::Testcase,
+        },
+        testing::{self, ValueRequest},
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_capture_per_destination() {
+        let capture = [
+            ("id".to_string(), Capture::Pointer("/data/createContent/id".to_string())),
+            ("count".to_string(), Capture::Pointer("/data/createContent/count".to_string())),
+            ("location".to_string(), Capture::Header("location".to_string())),
+        ];
+        let mut captures = Captures::new(capture.into_iter().collect());
+        let mut msg = Messages::<EvaluateError>::new();
+
+        captures.capture_json(&mut msg, "actual", &json!({"data": {"createContent": {"id": "a1", "count": 1}}}));
+        captures.capture_json(&mut msg, "expect", &json!({"data": {"createContent": {"id": "e1", "count": 2}}}));
+        captures.capture_header(&mut msg, "actual", |name| (name == "location").then(|| "/a1".to_string()));
+        assert_eq!(msg.len(), 0);
+
+        let captured = captures.into_captured();
+        assert_eq!(captured["actual"].render("${id} ${count} ${location}").unwrap(), "a1 1 /a1");
+        assert_eq!(captured["expect"].render("${id} ${count}").unwrap(), "e1 2");
+    }
+
+    #[test]
+    fn test_capture_not_found() {
+        let capture = [("id".to_string(), Capture::Pointer("/id".to_string()))];
+        let mut captures = Captures::new(capture.into_iter().collect());
+        let mut msg = Messages::<EvaluateError>::new();
+
+        captures.capture_body(&mut msg, "actual", br#"{"name": "content"}"#);
+        captures.capture_body(&mut msg, "expect", b"plain text");
+        assert_eq!(msg.len(), 2);
+        assert!(captures.captured().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_capture_into_later_testcases() {
+        let template = |seed: &str| Template::from_iter([("seed".to_string(), seed.to_string())]);
+        let capture = [("route", "/route"), ("id", "/content/id")]
+            .into_iter()
+            .map(|(var, pointer)| (var.to_string(), Capture::Pointer(pointer.to_string())));
+        let testcases = vec![
+            Testcase {
+                target: "/echo".to_string(),
+                profile: Profile {
+                    request: ValueRequest { value: Some(json!({"route": "echo", "content": {"id": "${seed}"}})) },
+                    template: [("a", template("a")), ("b", template("b"))].into_iter().collect(),
+                    capture: Some(capture.collect()),
+                    allow: Some(true),
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+            Testcase {
+                target: "/${route}".to_string(),
+                profile: Profile {
+                    request: ValueRequest { value: Some(json!("${id}")) },
+                    allow: Some(true),
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+        ];
+        let job = Job(vec![testing::suite("capture", ["a", "b"], testcases)]);
+
+        let report = testing::shot(&job, &JobSpec::default()).await.unwrap();
+        let cases = &report.suites[0].cases;
+        // captured route is same for each destination, but captured id differs
+        assert!(!cases[1].evaluated.pass);
+        assert_eq!(cases[1].messages.to_string(), "b vs a: not equal body\n");
+    }
+}
@@ -8,8 +8,9 @@ use semigroup::Semigroup;
 use tower::{Layer, MakeService, Service};
 
 use crate::{
-    evaluator::evaluate::{Failure, MessageExt, Messages},
-    shot::{destinations::Destinations, job::BasePath},
+    evaluator::evaluate::{Failure, MessageExt, Messages, Quorum},
+    record::metric::BodySizeRecorder,
+    shot::{capture::Captures, destinations::Destinations, job::BasePath, retry::RetryableError},
     template::Template,
 };
 
@@ -24,6 +25,21 @@ pub trait Contract<T>: Sized {
     type ResSink;
 
     type SignError;
+
+    /// Status code of the response, used to retry by status class.
+    fn status(_response: &Self::Response) -> Option<u16> {
+        None
+    }
+    /// Size of the request body before it is sent, such as the length of the HTTP body.
+    fn request_size(_request: &Self::Request) -> Option<u64> {
+        None
+    }
+    /// Recorder of the body sizes attached to the response, such as encoded gRPC messages recorded by the codec.
+    /// It is read after the response is consumed, so that the sink can record the size of the collected body.
+    /// The request size takes precedence over the one of [`Contract::request_size`].
+    fn body_size(_response: &mut Self::Response) -> BodySizeRecorder {
+        BodySizeRecorder::default()
+    }
 }
 pub type MakeError<M, T, C> = <M as MakeService<http::Uri, <C as Contract<T>>::TransportReq>>::MakeError;
 pub type TransportError<T, C> = <T as Service<<C as Contract<T>>::TransportReq>>::Error;
@@ -34,7 +50,7 @@ pub type ResSinkError<T, C> =
     <<C as Contract<T>>::ResSink as ResponseSink<Result<ServiceResponse<T, C>, ServiceError<T, C>>>>::Message;
 
 pub trait SignContract<T, C> {
-    type Error;
+    type Error: Display;
     fn sign_contract(
         &self,
         service: T,
@@ -45,14 +61,31 @@ pub trait SignContract<T, C> {
 
 #[trait_variant::make(Send)]
 pub trait RequestSource<De> {
-    type Error;
+    type Error: Display;
     async fn produce(&self, destination: &http::Uri, target: &str, template: &Template) -> Result<De, Self::Error>;
+    /// Human readable request that [`RequestSource::produce`] renders, for `--dry-run` without sending it.
+    async fn dry_run(&self, destination: &http::Uri, target: &str, template: &Template) -> Result<String, Self::Error>;
 }
 
 #[trait_variant::make(Send)]
 pub trait ResponseSink<Se> {
     type Message: MessageExt;
-    async fn consume(&self, msg: &mut Messages<Self::Message>, res: Destinations<Se>) -> Result<(), Failure>;
+    /// Evaluate responses of each destination, and capture values from them into `captures`.
+    /// With `quorum`, all destinations are compared with each other instead of with the baseline.
+    async fn consume(
+        &self,
+        msg: &mut Messages<Self::Message>,
+        captures: &mut Captures,
+        quorum: Option<&Quorum>,
+        res: Destinations<Se>,
+    ) -> Result<(), Failure>;
+    /// Resolve files referred by the sink, such as JSON Schema, relative to `base_path`.
+    fn with_base_path(self, _base_path: &Option<BasePath>) -> Self
+    where
+        Self: Sized,
+    {
+        self
+    }
 }
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Default, Hash, Semigroup)]
 #[semigroup(monoid, commutative, with = "semigroup::op::Sum")]
@@ -64,20 +97,26 @@ pub struct Evaluated {
     pub allow: bool,
     pub allowed: usize,
     pub times: usize,
+    pub retries: usize,
+    /// exceeded soft thresholds, such as latency of the testcase
+    #[semigroup(with = "semigroup::op::Any")]
+    pub poor: bool,
 }
 impl Evaluated {
     pub fn new<T, E>(evaluated: &Result<T, E>, allow: Option<bool>) -> Self {
         let pass = evaluated.is_ok();
         let allow = pass || allow.unwrap_or_default();
-        Self { pass, passed: pass as usize, allow, allowed: allow as usize, times: 1 }
+        Self { pass, passed: pass as usize, allow, allowed: allow as usize, times: 1, retries: 0, poor: false }
     }
     pub fn assess(&self) -> Assessment {
-        if self.pass {
+        if !self.allow {
+            Assessment::Bad
+        } else if self.poor {
+            Assessment::Poor
+        } else if self.pass {
             Assessment::Good
-        } else if self.allow {
-            Assessment::Acceptable
         } else {
-            Assessment::Bad
+            Assessment::Acceptable
         }
     }
 }
@@ -113,6 +152,11 @@ pub enum ContractErrorWrap<NE, TE, SE, QE, PE> {
     ResSink(PE),
     Timeout(Duration),
 }
+impl<NE, TE, SE, QE, PE> RetryableError for ContractErrorWrap<NE, TE, SE, QE, PE> {
+    fn is_timeout(&self) -> bool {
+        matches!(self, Self::Timeout(_))
+    }
+}
 impl<NE, TE, SE, QE, PE> std::error::Error for ContractErrorWrap<NE, TE, SE, QE, PE>
 where
     NE: std::error::Error + 'static,
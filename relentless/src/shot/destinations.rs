@@ -1,23 +1,30 @@
-use std::{
-    collections::HashMap,
-    ops::{Deref, DerefMut},
-};
+use std::ops::{Deref, DerefMut};
 
+use indexmap::IndexMap;
 use semigroup::Semigroup;
 use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Semigroup)]
+/// Values of each destination, kept in order of the config so that comparisons are deterministic.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(deny_unknown_fields, rename_all = "kebab-case")]
-pub struct Destinations<T>(#[semigroup(with = "semigroup::op::UnionMap")] HashMap<String, T>);
+pub struct Destinations<T>(IndexMap<String, T>);
 impl<T> Default for Destinations<T> {
     fn default() -> Self {
         // derive(Default) do not implement Default when T are not implement Default
         // https://github.com/rust-lang/rust/issues/26925
-        Self(HashMap::default())
+        Self(IndexMap::default())
+    }
+}
+impl<T> Semigroup for Destinations<T> {
+    fn op_assign(base: &mut Self, other: Self) {
+        // same as `semigroup::op::UnionMap`, but new destinations of `other` are appended in order
+        other.0.into_iter().for_each(|(k, v)| {
+            base.0.entry(k).or_insert(v);
+        });
     }
 }
 impl<T> Deref for Destinations<T> {
-    type Target = HashMap<String, T>;
+    type Target = IndexMap<String, T>;
     fn deref(&self) -> &Self::Target {
         &self.0
     }
@@ -28,15 +35,15 @@ impl<T> DerefMut for Destinations<T> {
     }
 }
 impl<T> IntoIterator for Destinations<T> {
-    type Item = <HashMap<String, T> as IntoIterator>::Item;
-    type IntoIter = <HashMap<String, T> as IntoIterator>::IntoIter;
+    type Item = <IndexMap<String, T> as IntoIterator>::Item;
+    type IntoIter = <IndexMap<String, T> as IntoIterator>::IntoIter;
     fn into_iter(self) -> Self::IntoIter {
         self.0.into_iter()
     }
 }
 impl<'a, T> IntoIterator for &'a Destinations<T> {
-    type Item = <&'a HashMap<String, T> as IntoIterator>::Item;
-    type IntoIter = <&'a HashMap<String, T> as IntoIterator>::IntoIter;
+    type Item = <&'a IndexMap<String, T> as IntoIterator>::Item;
+    type IntoIter = <&'a IndexMap<String, T> as IntoIterator>::IntoIter;
     fn into_iter(self) -> Self::IntoIter {
         self.0.iter()
     }
@@ -51,3 +58,52 @@ impl<S: Into<String>, T> Extend<(S, T)> for Destinations<T> {
         self.0.extend(iter.into_iter().map(|(d, v)| (d.into(), v)))
     }
 }
+
+impl<T> Destinations<T> {
+    /// Combine values of the same destination, unlike [`Semigroup`] of `Destinations` that keeps the first one.
+    pub fn merge(&mut self, other: Self)
+    where
+        T: Semigroup,
+    {
+        other.0.into_iter().for_each(|(k, v)| match self.0.entry(k) {
+            indexmap::map::Entry::Occupied(e) => e.into_mut().semigroup_assign(v),
+            indexmap::map::Entry::Vacant(e) => _ = e.insert(v),
+        });
+    }
+
+    /// Sort in order of `order`, and move the `baseline` to the front so that the others are compared with it.
+    /// Destinations not in `order`, such as ones added by the command line, keep their order at the end.
+    pub fn ordered<U>(mut self, order: &Destinations<U>, baseline: Option<&str>) -> Self {
+        self.0.sort_by_cached_key(|d, _| (Some(d.as_str()) != baseline, order.get_index_of(d).unwrap_or(usize::MAX)));
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_destinations_keep_config_order() {
+        let config: Destinations<_> = [("actual", 1), ("canary", 2), ("expect", 3)].into_iter().collect();
+        let overwrite: Destinations<_> = [("expect", 33), ("extra", 44), ("actual", 11)].into_iter().collect();
+
+        let combined = overwrite.semigroup(config.clone()).ordered(&config, None);
+        assert_eq!(
+            combined.iter().collect::<Vec<_>>(),
+            [(&"actual".into(), &11), (&"canary".into(), &2), (&"expect".into(), &33), (&"extra".into(), &44)]
+        );
+
+        let baseline = config.clone().ordered(&config, Some("expect"));
+        assert_eq!(baseline.keys().collect::<Vec<_>>(), ["expect", "actual", "canary"]);
+    }
+
+    #[test]
+    fn test_destinations_merge_each_value() {
+        let mut base: Destinations<_> =
+            [("actual", semigroup::op::Sum(1)), ("expect", semigroup::op::Sum(2))].into_iter().collect();
+        base.merge([("expect", semigroup::op::Sum(20)), ("extra", semigroup::op::Sum(30))].into_iter().collect());
+        let merged: Vec<_> = base.into_iter().map(|(d, v)| (d, v.0)).collect();
+        assert_eq!(merged, [("actual".to_string(), 1), ("expect".to_string(), 22), ("extra".to_string(), 30)]);
+    }
+}
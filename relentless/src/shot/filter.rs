@@ -0,0 +1,118 @@
+use regex::Regex;
+
+use crate::shot::{job::JobSpec, suite::Suite, testcase::Testcase};
+
+/// Select testcases to run by `--filter`, `--tag`, `--skip-tag`, and `only` / `skip` markers of the config.
+#[derive(Debug, Clone, Default)]
+pub struct Filter {
+    regex: Option<Regex>,
+    tags: Vec<String>,
+    skip_tags: Vec<String>,
+    /// some suites or testcases are marked as `only`, so the others are skipped
+    focused: bool,
+}
+impl Filter {
+    pub fn new(job: &JobSpec, focused: bool) -> crate::Result<Self> {
+        let regex = job.filter.as_deref().map(Regex::new).transpose().map_err(crate::Error::boxed)?;
+        Ok(Self { regex, tags: job.tag.clone(), skip_tags: job.skip_tag.clone(), focused })
+    }
+
+    /// Tags of the suite are inherited by its testcases, and the regex matches the suite name, target, or description.
+    pub fn skip<C, Q, P>(&self, suite: &Suite<C, Q, P>, case: &Testcase<Q, P>) -> bool {
+        let mut tags = suite.tags.iter().chain(&case.tags);
+        if suite.skip || case.skip || (self.focused && !(suite.only || case.only)) {
+            return true;
+        }
+        if let Some(regex) = &self.regex {
+            let mut names = [Some(&suite.name), Some(&case.target), case.description.as_ref()].into_iter().flatten();
+            if !names.any(|n| regex.is_match(n)) {
+                return true;
+            }
+        }
+        if !self.tags.is_empty() && !tags.clone().any(|t| self.tags.contains(t)) {
+            return true;
+        }
+        tags.any(|t| self.skip_tags.contains(t))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{shot::job::Job, testing};
+
+    use super::*;
+
+    fn testcase(target: &str, tags: &[&str]) -> Testcase<(), ()> {
+        let tags = tags.iter().map(|t| t.to_string()).collect();
+        Testcase { target: target.to_string(), tags, ..Default::default() }
+    }
+
+    #[test]
+    fn test_filter_by_regex_and_tags() {
+        let suite =
+            Suite::<(), (), ()> { name: "users".to_string(), tags: vec!["api".to_string()], ..Default::default() };
+        let (create, health) = (testcase("/users/create", &["slow"]), testcase("/health", &[]));
+
+        let job = JobSpec { filter: Some("create$".to_string()), ..Default::default() };
+        let filter = Filter::new(&job, false).unwrap();
+        assert!(!filter.skip(&suite, &create));
+        assert!(filter.skip(&suite, &health));
+
+        let job = JobSpec { tag: vec!["api".to_string()], skip_tag: vec!["slow".to_string()], ..Default::default() };
+        let filter = Filter::new(&job, false).unwrap();
+        assert!(filter.skip(&suite, &create));
+        assert!(!filter.skip(&suite, &health));
+
+        let job = JobSpec { filter: Some("(".to_string()), ..Default::default() };
+        assert!(Filter::new(&job, false).is_err());
+    }
+
+    #[test]
+    fn test_filter_by_markers() {
+        let suite = Suite::<(), (), ()> { name: "suite".to_string(), ..Default::default() };
+        let (only, skip) =
+            (Testcase { only: true, ..testcase("/only", &[]) }, Testcase { skip: true, ..testcase("/skip", &[]) });
+        let other = testcase("/other", &[]);
+
+        let filter = Filter::new(&JobSpec::default(), false).unwrap();
+        assert!(!filter.skip(&suite, &only));
+        assert!(filter.skip(&suite, &skip));
+        assert!(!filter.skip(&suite, &other));
+
+        let filter = Filter::new(&JobSpec::default(), true).unwrap();
+        assert!(!filter.skip(&suite, &only));
+        assert!(filter.skip(&suite, &other));
+        let suite = Suite { only: true, ..suite };
+        assert!(!filter.skip(&suite, &other));
+    }
+
+    #[tokio::test]
+    async fn test_skipped_testcases_are_reported() {
+        let testcase = |target: &str, tags: &[&str]| Testcase {
+            target: target.to_string(),
+            tags: tags.iter().map(|t| t.to_string()).collect(),
+            ..Default::default()
+        };
+        let job = Job(vec![
+            testing::suite("tagged", ["test"], vec![testcase("/echo", &["echo"]), testcase("/fail", &["fail"])]),
+            testing::suite(
+                "focused",
+                ["test"],
+                vec![Testcase { only: true, ..testcase("/echo", &[]) }, testcase("/echo", &["echo"])],
+            ),
+        ]);
+        let destination = vec![("test".to_string(), "http://localhost:3000".to_string())];
+        let spec = JobSpec { skip_tag: vec!["fail".to_string()], destination, ..Default::default() };
+
+        let report = testing::shot(&job, &spec).await.unwrap();
+        let skipped: Vec<Vec<_>> = report.suites.iter().map(|s| s.cases.iter().map(|c| c.skipped).collect()).collect();
+        assert_eq!(skipped, vec![vec![true, true], vec![false, true]]);
+        assert_eq!(report.evaluated.times, 1);
+        assert!(report.evaluated.pass);
+        let destinations = report.suites[0].destinations.combine_rev_clone();
+        assert_eq!(destinations["test"], "http://localhost:3000/"); // overridden even if all testcases are skipped
+
+        let spec = JobSpec { filter: Some("(".to_string()), ..Default::default() };
+        assert!(testing::shot(&job, &spec).await.is_err());
+    }
+}
@@ -20,7 +20,9 @@ use crate::{
     shot::{
         contract::{Contract, Evaluated, RequestSource, ResponseSink, ServiceError, SignContract},
         destinations::Destinations,
+        filter::Filter,
         hierarchy::Hierarchy,
+        scheduler::Arrival,
         suite::{SuiteCase, SuiteReport},
     },
 };
@@ -115,13 +117,33 @@ pub struct JobSpec {
     #[cfg_attr(feature = "cli", arg(env, long))]
     pub base_path: Option<BasePath>,
 
-    /// requests per second
+    /// requests per second of each testcase, requests are fired at this rate regardless of responses
     #[cfg_attr(feature = "cli", arg(env, long))]
     pub rps: Option<f64>,
 
-    /// duration
+    /// duration seconds to keep firing requests at `rps`, without this `repeat` requests are fired
     #[cfg_attr(feature = "cli", arg(env, long))]
     pub duration: Option<u64>, // TODO Duration
+
+    /// distribution of request arrivals at `rps`
+    #[cfg_attr(feature = "cli", arg(env, long, value_enum, default_value_t))]
+    pub arrival: Arrival,
+
+    /// run only testcases whose suite name, target, or description matches this regex
+    #[cfg_attr(feature = "cli", arg(env, long))]
+    pub filter: Option<String>,
+
+    /// run only testcases that have any of these tags, tags of the suite are inherited
+    #[cfg_attr(feature = "cli", arg(env, long, value_delimiter = ','))]
+    pub tag: Vec<String>,
+
+    /// skip testcases that have any of these tags
+    #[cfg_attr(feature = "cli", arg(env, long, value_delimiter = ','))]
+    pub skip_tag: Vec<String>,
+
+    /// render requests of each destination without sending them
+    #[cfg_attr(feature = "cli", arg(env, long))]
+    pub dry_run: bool,
 }
 impl JobSpec {
     pub fn destinations<U: Clone + Into<Uri>>(
@@ -149,7 +171,7 @@ impl BasePath {
     }
 }
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct Job<C, Q, P>(pub Vec<SuiteCase<C, Q, P>>);
 impl<C, Q, P> Job<C, Q, P>
 where
@@ -182,6 +204,7 @@ impl<S, Q, P> Job<S, Q, P> {
     ) -> crate::Result<JobReport<'_, S, Q, P, P::Message>>
     where
         M: Clone + MakeService<http::Uri, C::TransportReq, Service = T>,
+        M::MakeError: Display,
         T: Clone + Service<C::TransportReq, Response = C::TransportRes> + Send,
         S: Debug + SignContract<T, C> + Default,
         C: Contract<T, Sign = S, ReqSource = Q, ResSink = P> + Layer<T>,
@@ -191,8 +214,9 @@ impl<S, Q, P> Job<S, Q, P> {
         P::Message: Display,
     {
         let buffers = if Hierarchy::Job.contains(&job.sequential) { 1 } else { self.0.len().max(1) };
+        let filter = &Filter::new(job, self.0.iter().any(|sc| sc.focused()))?;
         let suites: Vec<_> = futures::stream::iter(&self.0)
-            .map(|sc| sc.shot(make_service.clone(), job))
+            .map(|sc| sc.shot(make_service.clone(), job, filter))
             .buffer_unordered(buffers)
             .try_collect()
             .await?;
@@ -200,3 +224,39 @@ impl<S, Q, P> Job<S, Q, P> {
         Ok(JobReport { suites, evaluated })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use crate::{
+        shot::{profile::Profile, testcase::Testcase},
+        testing::{self, ValueRequest},
+    };
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_suite_failure_is_reported() {
+        let testcase = Testcase {
+            target: "/echo".to_string(),
+            profile: Profile { request: ValueRequest { value: Some(json!("hello")) }, ..Default::default() },
+            ..Default::default()
+        };
+        let suites = vec![
+            testing::suite("first", ["test"], vec![testcase.clone()]),
+            testing::suite("second", ["test"], vec![testcase]),
+        ];
+        let job = Job(suites);
+        let spec = JobSpec { destination: vec![("test".to_string(), "invalid uri".to_string())], ..Default::default() };
+
+        let report = testing::shot(&job, &spec).await.unwrap();
+        assert_eq!(report.suites.len(), 2);
+        for suite in &report.suites {
+            assert!(!suite.evaluated.assess().success());
+            assert!(suite.cases.is_empty());
+            assert!(suite.messages.to_string().starts_with("invalid destination"));
+        }
+        assert!(!report.evaluated.assess().success());
+    }
+}
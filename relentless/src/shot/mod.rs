@@ -1,7 +1,11 @@
+pub mod capture;
 pub mod contract;
 pub mod destinations;
+pub mod filter;
 pub mod hierarchy;
 pub mod job;
 pub mod profile;
+pub mod retry;
+pub mod scheduler;
 pub mod suite;
 pub mod testcase;
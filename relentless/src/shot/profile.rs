@@ -1,20 +1,31 @@
-use std::{fmt::Debug, ops::Range, time::Duration};
+use std::{
+    collections::HashMap,
+    fmt::Debug,
+    ops::Range,
+    sync::Mutex,
+    time::{Duration, SystemTime},
+};
 
-use futures::{StreamExt, TryStreamExt};
-use semigroup::Semigroup;
+use futures::StreamExt;
+use semigroup::{Monoid, Semigroup};
 use serde::{Deserialize, Serialize};
-use tower::{Layer, Service, ServiceExt};
+use tower::{retry::Retry as RetryService, Layer, Service, ServiceExt};
 
 use crate::{
-    evaluator::evaluate::{MessageExt, Messages},
+    evaluator::evaluate::{Failure, MessageExt, Messages, Quorum},
+    record::metric::{BodySize, BodySizeRecorder, Metric, MetricAgg},
     shot::{
-        contract::{Contract, ContractError, Evaluated, RequestSource, ResponseSink, ServiceError},
+        capture::{self, Capture, Captures},
+        contract::{Contract, ContractError, Evaluated, RequestSource, ResSinkError, ResponseSink, ServiceError},
         destinations::Destinations,
+        job::{BasePath, JobSpec},
+        retry::{Retry, RetryPolicy},
+        scheduler::{Scheduler, Stage},
     },
     template::{self, Template},
 };
 
-#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize, Semigroup)]
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize, Semigroup)]
 #[serde(deny_unknown_fields, rename_all = "kebab-case")]
 pub struct Profile<Q, P> {
     #[serde(default)]
@@ -30,6 +41,36 @@ pub struct Profile<Q, P> {
     #[serde(default)]
     #[semigroup(with = "semigroup::op::Coalesce")]
     pub allow: Option<bool>,
+    /// stages of open model load, take precedence over `rps` and `duration` of the job
+    #[serde(default)]
+    #[semigroup(with = "semigroup::op::Coalesce")]
+    pub stages: Option<Vec<Stage>>,
+    /// requests before measurement, they are neither recorded in metrics nor evaluated
+    #[cfg_attr(feature = "yaml", serde(with = "serde_yaml::with::singleton_map_recursive"))]
+    #[serde(default)]
+    #[semigroup(with = "semigroup::op::Coalesce")]
+    pub warmup: Option<Warmup>,
+    #[cfg_attr(feature = "yaml", serde(with = "serde_yaml::with::singleton_map_recursive"))]
+    #[serde(default)]
+    #[semigroup(with = "semigroup::op::Coalesce")]
+    pub retry: Option<Retry>,
+    /// variables captured from responses of each destination, later testcases in the suite can use them in templates
+    #[cfg_attr(feature = "yaml", serde(with = "serde_yaml::with::singleton_map_recursive"))]
+    #[serde(default)]
+    #[semigroup(with = "semigroup::op::Coalesce")]
+    pub capture: Option<HashMap<String, Capture>>,
+    /// N-way comparison passes as acceptable if at least this number of destinations agree, the others are outliers
+    #[serde(default)]
+    #[semigroup(with = "semigroup::op::Coalesce")]
+    pub quorum: Option<Quorum>,
+    /// thresholds of the latency distribution of the testcase, exceeding `soft` is poor and exceeding `hard` is bad
+    #[serde(default)]
+    #[semigroup(with = "semigroup::op::Coalesce")]
+    pub latency: Option<Latency>,
+    /// latency regression of each destination against the baseline, such as a canary slower than the old version
+    #[serde(default)]
+    #[semigroup(with = "semigroup::op::Coalesce")]
+    pub regression: Option<Regression>,
 
     #[serde(default)]
     pub response: P,
@@ -48,15 +89,197 @@ impl Repeat {
     }
 }
 
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+pub enum Warmup {
+    /// number of requests
+    Requests(usize),
+    /// keep firing requests until this duration elapses
+    Duration(Duration),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Default, Hash, Serialize, Deserialize)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+pub struct Latency {
+    #[serde(default)]
+    pub soft: LatencyThreshold,
+    #[serde(default)]
+    pub hard: LatencyThreshold,
+}
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Default, Hash, Serialize, Deserialize)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+pub struct LatencyThreshold {
+    #[serde(default)]
+    pub p50: Option<Duration>,
+    #[serde(default)]
+    pub p99: Option<Duration>,
+    #[serde(default)]
+    pub max: Option<Duration>,
+}
+impl Latency {
+    /// Assess the latency distribution of requests to each destination, the result is merged into [`Evaluated`] of
+    /// the testcase. Exceeding `hard` thresholds fails unless `allow`, as well as other failures.
+    pub fn assess<M: MessageExt>(
+        &self,
+        msg: &mut Messages<M>,
+        metrics: &Destinations<MetricAgg>,
+        allow: Option<bool>,
+    ) -> Evaluated {
+        let exceeded = |threshold: &LatencyThreshold| -> Vec<_> {
+            metrics
+                .iter()
+                .flat_map(|(d, m)| threshold.exceeded(m).into_iter().map(move |e| format!("{d}: {e}")))
+                .collect()
+        };
+        let (hard, soft) = (exceeded(&self.hard), exceeded(&self.soft));
+        if !hard.is_empty() {
+            hard.into_iter().for_each(|e| _ = msg.error(M::custom(e)));
+            Evaluated { pass: false, allow: allow.unwrap_or_default(), ..Evaluated::identity() }
+        } else if !soft.is_empty() {
+            soft.into_iter().for_each(|e| msg.warn(M::custom(e)));
+            Evaluated { poor: true, ..Evaluated::identity() }
+        } else {
+            Evaluated::identity()
+        }
+    }
+}
+impl LatencyThreshold {
+    pub fn exceeded(&self, metric: &MetricAgg) -> Vec<String> {
+        [("p50", 0.5, self.p50), ("p99", 0.99, self.p99), ("max", 1.0, self.max)]
+            .into_iter()
+            .filter_map(|(name, quantile, threshold)| {
+                let (threshold, latency) = (threshold?, metric.approx_latency_quantile(quantile));
+                (latency > threshold).then(|| format!("{name} latency {latency:?} exceeds {threshold:?}"))
+            })
+            .collect()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+pub struct Regression {
+    /// quantile of latency to be compared, `0.95` by default
+    #[serde(default = "Regression::default_quantile")]
+    pub quantile: f64,
+    /// allowed ratio of slowdown, such as `0.2` for 20% slower than the baseline
+    pub threshold: f64,
+    /// significance level of Mann-Whitney U test, so that noise of small samples is not reported, `0.05` by default
+    #[serde(default = "Regression::default_significance")]
+    pub significance: f64,
+}
+impl Regression {
+    pub fn default_quantile() -> f64 {
+        0.95
+    }
+    pub fn default_significance() -> f64 {
+        0.05
+    }
+
+    /// Label of the quantile such as `p95` or `p99.9`.
+    pub fn percentile(&self) -> String {
+        let percentile = format!("{:.1}", self.quantile * 100.0);
+        format!("p{}", percentile.trim_end_matches('0').trim_end_matches('.'))
+    }
+
+    /// The first destination is the baseline, each of the others regresses if it is both slower than the threshold
+    /// at the quantile and significantly slower in the whole latency distribution. Regressions fail unless `allow`.
+    pub fn assess<M: MessageExt>(
+        &self,
+        msg: &mut Messages<M>,
+        latencies: &Destinations<MetricAgg>,
+        allow: Option<bool>,
+    ) -> Evaluated {
+        let mut latencies = latencies.iter();
+        let Some((baseline, base)) = latencies.next() else {
+            return Evaluated::identity();
+        };
+        let (percentile, base_latency) = (self.percentile(), base.approx_latency_quantile(self.quantile));
+        let regressions: Vec<_> = latencies
+            .filter_map(|(target, agg)| {
+                let latency = agg.approx_latency_quantile(self.quantile);
+                let slower = latency.as_secs_f64() > base_latency.as_secs_f64() * (1.0 + self.threshold);
+                let p_value = agg.latency_greater_p_value(base)?;
+                (slower && p_value < self.significance).then(|| {
+                    let ratio = (latency.as_secs_f64() / base_latency.as_secs_f64() - 1.0) * 100.0;
+                    let message = format!("{percentile} latency {latency:?} is {ratio:.0}% slower than {base_latency:?} (p-value {p_value:.3})");
+                    M::custom(message).compared(target, baseline)
+                })
+            })
+            .collect();
+        if regressions.is_empty() {
+            Evaluated::identity()
+        } else {
+            regressions.into_iter().for_each(|e| _ = msg.error(e));
+            Evaluated { pass: false, allow: allow.unwrap_or_default(), ..Evaluated::identity() }
+        }
+    }
+}
+
 impl<Q, P> Profile<Q, P> {
-    #[allow(clippy::type_complexity)] // TODO
+    /// Assign captured variables of each destination to the template, they take precedence over configured ones.
+    pub fn with_captured(mut self, captured: &Destinations<Template>) -> Self {
+        capture::merge(&mut self.template, captured.clone());
+        self
+    }
+
+    /// Resolve files referred by the response, relative to `base_path` of the job.
+    pub fn with_base_path<Se>(mut self, base_path: &Option<BasePath>) -> Self
+    where
+        P: ResponseSink<Se>,
+    {
+        self.response = self.response.with_base_path(base_path);
+        self
+    }
+
+    /// Scheduler of open model load, `None` means requests are fired `repeat` times as soon as possible.
+    pub fn scheduler(&self, job: &JobSpec) -> Option<Scheduler> {
+        let stages = match (&self.stages, job.rps, job.duration) {
+            (Some(stages), _, _) => stages.clone(),
+            (None, Some(rps), Some(duration)) => vec![Stage::constant(rps, Duration::from_secs(duration))],
+            (None, Some(rps), None) => {
+                let stages = vec![Stage::constant(rps, Duration::MAX)];
+                return Some(Scheduler::new(stages, job.arrival.clone(), Some(self.repeat.times())));
+            }
+            (None, None, _) => return None,
+        };
+        Some(Scheduler::new(stages, job.arrival.clone(), None))
+    }
+
+    /// Fire warm-up requests one by one through the same services, their results and metrics are discarded.
+    #[tracing::instrument(name = "warmup", skip(services))]
+    pub async fn warmup<T, C>(
+        &self,
+        services: &Destinations<C::Service>,
+        destinations: &Destinations<http::Uri>,
+        target: &str,
+    ) where
+        T: Service<C::TransportReq, Response = C::TransportRes>,
+        C: Contract<T, ReqSource = Q, ResSink = P> + Layer<T>,
+        C::Service: Clone + Service<C::Request, Response = C::Response>,
+        Q: Debug + RequestSource<C::Request>,
+        P: Debug + ResponseSink<Result<C::Response, ServiceError<T, C>>>,
+    {
+        let shot = || self.shot::<T, C>(services, destinations, target);
+        match &self.warmup {
+            Some(Warmup::Requests(n)) => futures::stream::iter(0..*n).then(|_| shot()).for_each(|_| async {}).await,
+            Some(Warmup::Duration(d)) => {
+                let start = tokio::time::Instant::now();
+                while start.elapsed() < *d {
+                    shot().await;
+                }
+            }
+            None => (),
+        }
+    }
+
+    /// Errors before consuming responses, such as failure of producing requests or timeout, are evaluated as failure.
     #[tracing::instrument(name = "profile", skip(services))]
     pub async fn shot<T, C>(
         &self,
         services: &Destinations<C::Service>,
         destinations: &Destinations<http::Uri>,
         target: &str,
-    ) -> Result<(Evaluated, Messages<P::Message>), ContractError<T, C>>
+    ) -> (Evaluated, Messages<P::Message>, Destinations<Template>, Destinations<MetricAgg>)
     where
         T: Service<C::TransportReq, Response = C::TransportRes>,
         C: Contract<T, ReqSource = Q, ResSink = P> + Layer<T>,
@@ -65,42 +288,221 @@ impl<Q, P> Profile<Q, P> {
         P: Debug + ResponseSink<Result<C::Response, ServiceError<T, C>>>,
     {
         let buffers = services.len().max(1);
-        let responses = futures::stream::iter(services)
+        let results: Vec<_> = futures::stream::iter(services)
             .map(|(name, service)| {
                 let template = self.template.get(name).cloned().unwrap_or_default();
                 async move {
-                    let destination = destinations.get(name).unwrap_or_else(|| todo!());
-                    let request = self
-                        .request
-                        .produce(destination, target, &template)
-                        .await
-                        .map_err(ContractError::<T, C>::ReqSource)?;
-                    let service = service.clone().oneshot(request);
-                    let response = if let Some(timeout) = self.timeout {
-                        match tokio::time::timeout(timeout, service).await {
-                            Ok(response) => response,
-                            Err(_) => Err(ContractError::<T, C>::Timeout(timeout))?,
+                    let (destination, template) =
+                        (destinations.get(name).expect("services are made for each destination"), &template);
+                    // of the last attempt, so latency excludes failed attempts and backoff of retries
+                    let started = &Mutex::new((SystemTime::now(), tokio::time::Instant::now()));
+                    let request_size = &Mutex::new(None);
+                    let call = tower::service_fn(|()| async move {
+                        *started.lock().unwrap() = (SystemTime::now(), tokio::time::Instant::now());
+                        let request = self
+                            .request
+                            .produce(destination, target, template)
+                            .await
+                            .map_err(ContractError::<T, C>::ReqSource)?;
+                        *request_size.lock().unwrap() = C::request_size(&request);
+                        let service = service.clone().oneshot(request);
+                        if let Some(timeout) = self.timeout {
+                            match tokio::time::timeout(timeout, service).await {
+                                Ok(response) => Ok(response),
+                                Err(_) => Err(ContractError::<T, C>::Timeout(timeout)),
+                            }
+                        } else {
+                            Ok(service.await)
                         }
-                    } else {
-                        service.await
+                    });
+                    let (mut response, retries) = match &self.retry {
+                        Some(retry) => {
+                            let policy = RetryPolicy::new(retry.clone(), C::status);
+                            (RetryService::new(policy.clone(), call).oneshot(()).await, policy.retries())
+                        }
+                        None => (call.oneshot(()).await, 0),
+                    };
+                    let ((timestamp, start), end) = (*started.lock().unwrap(), tokio::time::Instant::now());
+                    let recorder = match &mut response {
+                        Ok(Ok(response)) => C::body_size(response),
+                        _ => BodySizeRecorder::default(),
+                    };
+                    let request_size = *request_size.lock().unwrap();
+                    let metric = move || {
+                        let bytes = recorder.body_size();
+                        let bytes = BodySize { request: bytes.request.or(request_size), ..bytes };
+                        (name.clone(), Metric::new(bytes, timestamp, (start.into_std(), end.into_std())).into_agg())
                     };
-                    Ok::<_, ContractError<T, C>>((name, response))
+                    (retries, metric, response.map(|response| (name, response)))
                 }
             })
-            .buffer_unordered(buffers)
-            .try_collect()
+            .buffered(buffers) // keep order of destinations to compare with the baseline
+            .collect()
             .await;
-        let mut messages = Messages::new();
+        let retries = results.iter().map(|(retries, _, _)| retries).sum();
+        let (metrics, responses): (Vec<_>, Vec<_>) =
+            results.into_iter().map(|(_, metric, response)| (metric, response)).unzip();
+        let responses: Result<Destinations<_>, _> = responses.into_iter().collect();
+        // body sizes may be recorded while consuming responses, such as a streaming body collected by the sink
+        let metrics = || metrics.into_iter().map(|metric| metric()).collect();
+        let (mut messages, mut captures) = (Messages::new(), Captures::new(self.capture.clone().unwrap_or_default()));
         match responses {
             Ok(responses) => {
-                let evaluated = self.response.consume(&mut messages, responses).await;
-                Ok((Evaluated::new(&evaluated, self.allow), messages))
+                let evaluated =
+                    self.response.consume(&mut messages, &mut captures, self.quorum.as_ref(), responses).await;
+                let allow = matches!(evaluated, Err(Failure { acceptable: true })) || self.allow.unwrap_or_default();
+                let evaluated = Evaluated { retries, ..Evaluated::new(&evaluated, Some(allow)) };
+                (evaluated, messages, captures.into_captured(), metrics())
             }
-            e @ Err(ContractError::<T, C>::Timeout(t)) => {
-                messages.error(<P as ResponseSink<Result<C::Response, ServiceError<T, C>>>>::Message::timeout(t));
-                Ok((Evaluated::new(&e, self.allow), messages))
+            Err(e) => {
+                let message = match e {
+                    ContractError::<T, C>::Timeout(t) => ResSinkError::<T, C>::timeout(t),
+                    ContractError::<T, C>::ReqSource(e) => ResSinkError::<T, C>::custom(e),
+                    _ => unreachable!("only request source errors and timeouts occur before consuming responses"),
+                };
+                messages.error(message);
+                (
+                    Evaluated { retries, ..Evaluated::new(&Err::<(), _>(()), self.allow) },
+                    messages,
+                    captures.into_captured(),
+                    metrics(),
+                )
             }
-            _ => todo!(),
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use crate::{
+        shot::{contract::Assessment, job::Job, testcase::Testcase},
+        testing::{self, ValueRequest},
+    };
+
+    use super::*;
+
+    #[tokio::test(start_paused = true)]
+    async fn test_warmup_is_not_evaluated() {
+        let testcase = Testcase {
+            target: "/wait".to_string(),
+            profile: Profile {
+                request: ValueRequest { value: Some(json!(100)) },
+                repeat: Repeat(Some(2)),
+                warmup: Some(Warmup::Requests(3)),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let job = Job(vec![testing::suite("/wait", ["test"], vec![testcase])]);
+
+        let start = tokio::time::Instant::now();
+        let report = testing::shot(&job, &JobSpec::default()).await.unwrap();
+        assert!(start.elapsed() >= Duration::from_millis(400));
+        assert_eq!(report.evaluated.times, 2);
+        assert!(report.evaluated.assess().success());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_latency_thresholds() {
+        let millis = Duration::from_millis;
+        let testcase = |latency, allow| Testcase {
+            target: "/wait".to_string(),
+            profile: Profile {
+                request: ValueRequest { value: Some(json!(100)) },
+                repeat: Repeat(Some(3)),
+                latency: Some(latency),
+                allow,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let testcases = vec![
+            testcase(
+                Latency {
+                    hard: LatencyThreshold { max: Some(millis(1000)), ..Default::default() },
+                    ..Default::default()
+                },
+                None,
+            ),
+            testcase(
+                Latency {
+                    soft: LatencyThreshold { p50: Some(millis(50)), ..Default::default() },
+                    hard: LatencyThreshold { max: Some(millis(1000)), ..Default::default() },
+                },
+                None,
+            ),
+            testcase(
+                Latency {
+                    soft: LatencyThreshold { p50: Some(millis(50)), ..Default::default() },
+                    hard: LatencyThreshold { p99: Some(millis(50)), ..Default::default() },
+                },
+                None,
+            ),
+            testcase(
+                Latency {
+                    hard: LatencyThreshold { p99: Some(millis(50)), ..Default::default() },
+                    ..Default::default()
+                },
+                Some(true),
+            ),
+        ];
+        let job = Job(vec![testing::suite("/wait", ["test"], testcases)]);
+
+        let report = testing::shot(&job, &JobSpec::default()).await.unwrap();
+        let cases = &report.suites[0].cases;
+        assert_eq!(cases[0].evaluated.assess(), Assessment::Good);
+        assert!(cases[0].messages.is_empty());
+        assert_eq!(cases[1].evaluated.assess(), Assessment::Poor);
+        assert_eq!(cases[1].messages.to_string(), "test: p50 latency 100ms exceeds 50ms\n");
+        assert_eq!(cases[2].evaluated.assess(), Assessment::Bad);
+        assert!(cases[2].messages.to_string().starts_with("test: p99 latency "));
+        assert_eq!(cases[3].evaluated.assess(), Assessment::Acceptable);
+        assert_eq!(report.evaluated.assess(), Assessment::Bad);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_latency_regression_against_baseline() {
+        let template = |delay: &str| Template::from_iter([("delay".to_string(), delay.to_string())]);
+        let testcase = |new, repeat| Testcase {
+            target: "/delay".to_string(),
+            profile: Profile {
+                request: ValueRequest { value: Some(json!("${delay}")) },
+                template: [("old", template("10")), ("new", template(new))].into_iter().collect(),
+                repeat: Repeat(Some(repeat)),
+                regression: Some(serde_json::from_value(json!({"threshold": 0.2})).unwrap()),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let testcases = vec![testcase("10", 20), testcase("50", 20), testcase("50", 1)];
+        let job = Job(vec![testing::suite("regression", ["old", "new"], testcases)]);
+
+        let report = testing::shot(&job, &JobSpec::default()).await.unwrap();
+        let cases = &report.suites[0].cases;
+        assert_eq!(cases[0].evaluated.assess(), Assessment::Good);
+        assert_eq!(cases[1].evaluated.assess(), Assessment::Bad);
+        assert!(cases[1].messages.to_string().starts_with("new vs old: p95 latency "));
+        // a single sample is not significant
+        assert_eq!(cases[2].evaluated.assess(), Assessment::Good);
+
+        assert_eq!(cases[1].metrics.keys().collect::<Vec<_>>(), ["old", "new"]);
+        assert_eq!(cases[1].metrics["new"].approx_latency_quantile(0.5), Duration::from_millis(50));
+        assert_eq!(report.suites[0].metrics["old"].times(), 41);
+        // `"50"` is requested and `null` is responded
+        let bytes = bytesize::ByteSize::b(4);
+        assert_eq!(cases[1].metrics["new"].approx_request_bytes_quantile(0.5), Some(bytes));
+        assert_eq!(cases[1].metrics["new"].approx_response_bytes_quantile(0.99), Some(bytes));
+    }
+
+    #[test]
+    fn test_regression_percentile() {
+        let regression =
+            |quantile| Regression { quantile, threshold: 0.2, significance: Regression::default_significance() };
+        assert_eq!(regression(0.95).percentile(), "p95");
+        assert_eq!(regression(0.29).percentile(), "p29");
+        assert_eq!(regression(0.999).percentile(), "p99.9");
+        assert_eq!(regression(1.0).percentile(), "p100");
+    }
+}
@@ -0,0 +1,218 @@
+use std::{
+    future::Future,
+    pin::Pin,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+pub struct Retry {
+    /// max attempts including the first request
+    pub max_attempts: usize,
+    /// wait before the first retry, doubled for each of following retries
+    #[serde(default)]
+    pub backoff: Option<Duration>,
+    /// upper bound of the doubled backoff
+    #[serde(default)]
+    pub max_backoff: Option<Duration>,
+    /// ratio of random variation of the backoff, such as `0.1` for ±10%
+    #[serde(default)]
+    pub jitter: Option<f64>,
+    #[serde(default = "Retryable::defaults")]
+    pub retryable: Vec<Retryable>,
+}
+impl Retry {
+    /// Bounded by `max_backoff`, and it never overflows even if it is doubled many times.
+    pub fn backoff(&self, retries: usize) -> Duration {
+        let jitter = self.jitter.unwrap_or_default() * (2.0 * rand::random::<f64>() - 1.0);
+        let factor = 2f64.powi(retries.min(64) as i32) * (1.0 + jitter).max(0.0);
+        let backoff = self.backoff.unwrap_or_default().as_secs_f64() * factor;
+        let backoff = Duration::try_from_secs_f64(backoff).unwrap_or(Duration::MAX);
+        backoff.min(self.max_backoff.unwrap_or(Duration::MAX))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+pub enum Retryable {
+    /// error of the service, such as connection refused
+    Transport,
+    /// request timed out
+    Timeout,
+    /// status code of the class, such as `5` for 5xx of HTTP
+    StatusClass(u16),
+}
+impl Retryable {
+    pub fn defaults() -> Vec<Self> {
+        vec![Self::Transport, Self::Timeout]
+    }
+}
+
+/// [`tower::retry::Policy`] that counts retries of all requests in `retries`.
+pub struct RetryPolicy<R> {
+    retry: Retry,
+    status: fn(&R) -> Option<u16>,
+    attempts: usize,
+    retries: Arc<AtomicUsize>,
+}
+impl<R> Clone for RetryPolicy<R> {
+    fn clone(&self) -> Self {
+        let Self { retry, status, attempts, retries } = self;
+        Self { retry: retry.clone(), status: *status, attempts: *attempts, retries: retries.clone() }
+    }
+}
+impl<R> RetryPolicy<R> {
+    pub fn new(retry: Retry, status: fn(&R) -> Option<u16>) -> Self {
+        Self { retry, status, attempts: 1, retries: Default::default() }
+    }
+    pub fn retries(&self) -> usize {
+        self.retries.load(Ordering::Relaxed)
+    }
+    fn retryable<SE, E: RetryableError>(&self, result: &Result<Result<R, SE>, E>) -> bool {
+        self.retry.retryable.iter().any(|r| match (r, result) {
+            (Retryable::Transport, Ok(Err(_))) => true,
+            (Retryable::Timeout, Err(e)) => e.is_timeout(),
+            (Retryable::StatusClass(class), Ok(Ok(res))) => (self.status)(res).is_some_and(|s| s / 100 == *class),
+            _ => false,
+        })
+    }
+}
+impl<R, SE, E: RetryableError> tower::retry::Policy<(), Result<R, SE>, E> for RetryPolicy<R> {
+    type Future = Pin<Box<dyn Future<Output = ()> + Send>>;
+
+    fn retry(&mut self, _: &mut (), result: &mut Result<Result<R, SE>, E>) -> Option<Self::Future> {
+        if self.attempts >= self.retry.max_attempts || !self.retryable(result) {
+            return None;
+        }
+        let backoff = self.retry.backoff(self.attempts - 1);
+        self.attempts += 1;
+        self.retries.fetch_add(1, Ordering::Relaxed);
+        Some(Box::pin(tokio::time::sleep(backoff)))
+    }
+
+    fn clone_request(&mut self, _: &()) -> Option<()> {
+        Some(())
+    }
+}
+
+/// Errors before the response of the service, only timeout can be retried.
+pub trait RetryableError {
+    fn is_timeout(&self) -> bool;
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use tower::{retry::Retry as RetryService, ServiceExt};
+
+    use crate::{
+        shot::{
+            job::{Job, JobSpec},
+            profile::Profile,
+            testcase::Testcase,
+        },
+        testing::{self, ValueRequest},
+    };
+
+    use super::*;
+
+    #[derive(Debug)]
+    struct Timeout;
+    impl RetryableError for Timeout {
+        fn is_timeout(&self) -> bool {
+            true
+        }
+    }
+
+    #[tokio::test]
+    async fn test_retry_until_success() {
+        let count = Arc::new(AtomicUsize::new(0));
+        let service = tower::service_fn(|()| {
+            let count = count.clone();
+            async move {
+                match count.fetch_add(1, Ordering::Relaxed) {
+                    0 => Err(Timeout),
+                    1 => Ok(Err("connection refused")),
+                    _ => Ok(Ok(200)),
+                }
+            }
+        });
+        let retry = Retry { max_attempts: 5, retryable: Retryable::defaults(), ..Default::default() };
+        let policy = RetryPolicy::new(retry, |s: &u16| Some(*s));
+        let response = RetryService::new(policy.clone(), service).oneshot(()).await;
+        assert!(matches!(response, Ok(Ok(200))));
+        assert_eq!(policy.retries(), 2);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_retry_status_class() {
+        let service = tower::service_fn(|()| async { Ok::<_, Timeout>(Ok::<_, ()>(503)) });
+        let retryable = vec![Retryable::StatusClass(5)];
+        let backoff = Some(Duration::from_millis(10));
+        let retry = Retry { max_attempts: 3, backoff, retryable, ..Default::default() };
+        let policy = RetryPolicy::new(retry, |s: &u16| Some(*s));
+        let start = tokio::time::Instant::now();
+        let response = RetryService::new(policy.clone(), service).oneshot(()).await;
+        assert!(matches!(response, Ok(Ok(503))));
+        assert_eq!(policy.retries(), 2);
+        assert!(start.elapsed() >= Duration::from_millis(30));
+    }
+
+    #[test]
+    fn test_backoff_jitter() {
+        let retry = Retry { backoff: Some(Duration::from_millis(100)), jitter: Some(0.5), ..Default::default() };
+        assert!((Duration::from_millis(50)..=Duration::from_millis(150)).contains(&retry.backoff(0)));
+        assert!((Duration::from_millis(200)..=Duration::from_millis(600)).contains(&retry.backoff(2)));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_max_backoff() {
+        let backoff = Some(Duration::from_secs(1));
+        let retry = Retry { backoff, jitter: Some(0.5), ..Default::default() };
+        assert!(retry.backoff(100) > Duration::from_secs(1 << 62));
+        assert!(retry.backoff(usize::MAX) > Duration::from_secs(1 << 62));
+
+        let service = tower::service_fn(|()| async { Err::<Result<u16, ()>, _>(Timeout) });
+        let max_backoff = Some(Duration::from_secs(10));
+        let retry = Retry { max_attempts: 100, backoff, max_backoff, retryable: Retryable::defaults(), jitter: None };
+        let policy = RetryPolicy::new(retry, |s: &u16| Some(*s));
+        let start = tokio::time::Instant::now();
+        assert!(RetryService::new(policy.clone(), service).oneshot(()).await.is_err());
+        assert_eq!(policy.retries(), 99);
+        assert_eq!(start.elapsed(), Duration::from_secs(1 + 2 + 4 + 8 + 10 * 95));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_retry_timeout() {
+        let retry = Retry {
+            max_attempts: 3,
+            backoff: Some(Duration::from_secs(1)),
+            retryable: vec![Retryable::Timeout],
+            ..Default::default()
+        };
+        let testcase = Testcase {
+            target: "/wait".to_string(),
+            profile: Profile {
+                request: ValueRequest { value: Some(json!(1000)) },
+                timeout: Some(Duration::from_millis(50)),
+                retry: Some(retry),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let job = Job(vec![testing::suite("/wait", ["test"], vec![testcase])]);
+
+        let report = testing::shot(&job, &JobSpec::default()).await.unwrap();
+        assert_eq!((report.evaluated.times, report.evaluated.retries), (1, 2));
+        assert!(!report.evaluated.assess().success());
+        let metric = report.suites[0].cases[0].metrics.get("test").unwrap();
+        assert!(metric.approx_latency_quantile(1.0) < Duration::from_millis(100));
+        // only the last attempt
+    }
+}
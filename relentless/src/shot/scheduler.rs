@@ -0,0 +1,287 @@
+use std::time::Duration;
+
+use futures::{Stream, StreamExt};
+use serde::{Deserialize, Serialize};
+
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Default, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Arrival {
+    /// fire requests at a constant interval
+    #[default]
+    Constant,
+
+    /// fire requests with exponentially distributed intervals
+    Poisson,
+}
+impl Arrival {
+    /// Interval to the next arrival, in units of the mean interval.
+    pub fn interval(&self) -> f64 {
+        match self {
+            Self::Constant => 1.0,
+            Self::Poisson => -(1.0 - rand::random::<f64>()).ln(),
+        }
+    }
+}
+
+/// Segment of load, the rate changes linearly from `from` to `target` over `duration`.
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+pub struct Stage {
+    pub duration: Duration,
+    /// requests per second at the end of this stage
+    pub target: f64,
+    /// requests per second at the start of this stage, default is the target of the previous stage (or 0)
+    #[serde(default)]
+    pub from: Option<f64>,
+}
+impl Stage {
+    pub fn constant(rps: f64, duration: Duration) -> Self {
+        Self { duration, target: rps, from: Some(rps) }
+    }
+    fn rates(&self, previous: f64) -> (f64, f64) {
+        (self.from.unwrap_or(previous), self.target)
+    }
+    /// Expected number of arrivals until `elapsed` in this stage.
+    fn cumulative(&self, previous: f64, elapsed: f64) -> f64 {
+        let ((a, b), t) = (self.rates(previous), self.duration.as_secs_f64());
+        a * elapsed + (b - a) * elapsed * elapsed / (2.0 * t)
+    }
+    /// Inverse of [`Stage::cumulative`], elapsed time in this stage until `count` arrivals are expected.
+    fn elapsed(&self, previous: f64, count: f64) -> f64 {
+        let ((a, b), t) = (self.rates(previous), self.duration.as_secs_f64());
+        if (b - a).abs() < f64::EPSILON {
+            count / a
+        } else {
+            ((a * a + 2.0 * (b - a) * count / t).max(0.0).sqrt() - a) * t / (b - a)
+        }
+    }
+}
+
+/// Open model scheduler, that fires requests at the target arrival rate regardless of how slowly responses come back.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Scheduler {
+    pub stages: Vec<Stage>,
+    pub arrival: Arrival,
+    pub limit: Option<usize>,
+}
+impl Scheduler {
+    pub fn new(stages: Vec<Stage>, arrival: Arrival, limit: Option<usize>) -> Self {
+        Self { stages, arrival, limit }
+    }
+
+    /// Offsets from the start of the schedule at which each request should be fired, with the index of its stage.
+    /// Bounded by the total duration of stages, and `limit` if it is given.
+    pub fn arrivals(&self) -> impl Iterator<Item = (usize, Duration)> {
+        let Self { stages, arrival, limit } = self.clone();
+        let (mut stage, mut previous, mut start, mut counted) = (0, 0.0, 0.0, 0.0);
+        let mut count = 0.0;
+        let offsets = std::iter::from_fn(move || loop {
+            let current = stages.get(stage)?;
+            let expected = current.cumulative(previous, current.duration.as_secs_f64());
+            if count - counted < expected {
+                let offset = start + current.elapsed(previous, count - counted);
+                count += arrival.interval();
+                return Some((stage, Duration::from_secs_f64(offset)));
+            }
+            (stage, previous, counted) = (stage + 1, current.target, counted + expected);
+            start += current.duration.as_secs_f64();
+        });
+        offsets.take(limit.unwrap_or(usize::MAX))
+    }
+
+    /// Stream that yields the index of the stage of each request at its arrival time.
+    pub fn ticks(&self) -> impl Stream<Item = usize> {
+        let start = tokio::time::Instant::now();
+        futures::stream::iter(self.arrivals()).then(move |(stage, offset)| async move {
+            tokio::time::sleep_until(start + offset).await;
+            stage
+        })
+    }
+}
+
+/// Closed model, each of `count` virtual users loops over testcases of a suite in order,
+/// so in-flight requests are bounded by the number of users.
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+pub struct VirtualUsers {
+    pub count: usize,
+    /// iterations of each user, default is 1 without `duration`
+    #[serde(default)]
+    pub iterations: Option<usize>,
+    /// each user keeps iterating until this duration elapses
+    #[serde(default)]
+    pub duration: Option<Duration>,
+    /// pause of each user between iterations
+    #[serde(default)]
+    pub think_time: Option<Duration>,
+}
+impl VirtualUsers {
+    /// Stream that yields the index of each iteration of a user, after the think time from the previous one.
+    pub fn iterations(&self) -> impl Stream<Item = usize> {
+        let Self { iterations, duration, think_time, .. } = self.clone();
+        let limit = iterations.unwrap_or(if duration.is_some() { usize::MAX } else { 1 });
+        let deadline = duration.and_then(|d| tokio::time::Instant::now().checked_add(d));
+        futures::stream::iter(0..limit)
+            .then(move |i| async move {
+                if let (true, Some(think_time)) = (i > 0, think_time) {
+                    tokio::time::sleep(think_time).await;
+                }
+                i
+            })
+            .take_while(move |_| std::future::ready(deadline.is_none_or(|d| tokio::time::Instant::now() < d)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use crate::{
+        shot::{
+            job::{Job, JobSpec},
+            profile::Profile,
+            testcase::Testcase,
+        },
+        testing::{self, ValueRequest},
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_constant_arrivals() {
+        let scheduler = Scheduler::new(vec![Stage::constant(10.0, Duration::from_secs(2))], Arrival::Constant, None);
+        let arrivals: Vec<_> = scheduler.arrivals().collect();
+        assert_eq!(arrivals.len(), 20);
+        assert_eq!(arrivals[0], (0, Duration::ZERO));
+        assert_eq!(arrivals[10], (0, Duration::from_secs(1)));
+
+        let scheduler = Scheduler::new(vec![Stage::constant(10.0, Duration::MAX)], Arrival::Constant, Some(5));
+        let arrivals: Vec<_> = scheduler.arrivals().collect();
+        assert_eq!(arrivals.len(), 5);
+        assert_eq!(arrivals[4], (0, Duration::from_millis(400)));
+    }
+
+    #[test]
+    fn test_poisson_arrivals() {
+        let scheduler = Scheduler::new(vec![Stage::constant(1000.0, Duration::from_secs(10))], Arrival::Poisson, None);
+        let arrivals: Vec<_> = scheduler.arrivals().collect();
+        assert!((9000..11000).contains(&arrivals.len()));
+        assert!(arrivals.windows(2).all(|w| w[0] <= w[1]));
+        assert!(arrivals.iter().all(|(_, a)| a < &Duration::from_secs(10)));
+    }
+
+    #[test]
+    fn test_staged_arrivals() {
+        let stages = vec![
+            Stage { duration: Duration::from_secs(10), target: 20.0, from: Some(10.0) },
+            Stage { duration: Duration::from_secs(5), target: 20.0, from: None },
+            Stage { duration: Duration::from_secs(10), target: 0.0, from: None },
+        ];
+        let scheduler = Scheduler::new(stages, Arrival::Constant, None);
+        let arrivals: Vec<_> = scheduler.arrivals().collect();
+        let count = |stage| arrivals.iter().filter(|(s, _)| *s == stage).count();
+        assert_eq!((count(0), count(1), count(2)), (150, 100, 100));
+        assert!(arrivals.windows(2).all(|w| w[0] <= w[1]));
+        assert!(arrivals.iter().all(|(_, a)| a < &Duration::from_secs(25)));
+
+        // ramp up: intervals become shorter
+        let (first, last) = (arrivals[1].1 - arrivals[0].1, arrivals[149].1 - arrivals[148].1);
+        assert!(first > last);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_ticks_do_not_wait_responses() {
+        let scheduler = Scheduler::new(vec![Stage::constant(50.0, Duration::from_secs(1))], Arrival::Constant, None);
+        let start = tokio::time::Instant::now();
+        let count = scheduler
+            .ticks()
+            .map(|_| tokio::time::sleep(Duration::from_millis(500)))
+            .buffer_unordered(usize::MAX)
+            .count()
+            .await;
+        assert_eq!(count, 50);
+        assert!(start.elapsed() < Duration::from_millis(1600));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_virtual_user_iterations() {
+        let users = VirtualUsers { count: 1, ..Default::default() };
+        assert_eq!(users.iterations().collect::<Vec<_>>().await, vec![0]);
+
+        let think_time = Some(Duration::from_millis(100));
+        let users = VirtualUsers { count: 1, iterations: Some(3), think_time, ..Default::default() };
+        let start = tokio::time::Instant::now();
+        assert_eq!(users.iterations().count().await, 3);
+        assert!((Duration::from_millis(200)..Duration::from_millis(300)).contains(&start.elapsed()));
+
+        let duration = Some(Duration::from_millis(450));
+        let users = VirtualUsers { count: 1, duration, think_time, ..Default::default() };
+        assert_eq!(users.iterations().count().await, 5);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_open_model_with_rps() {
+        let testcase = Testcase {
+            target: "/wait".to_string(),
+            profile: Profile { request: ValueRequest { value: Some(json!(500)) }, ..Default::default() },
+            ..Default::default()
+        };
+        let job = Job(vec![testing::suite("/wait", ["test"], vec![testcase])]);
+        let spec = JobSpec { rps: Some(20.0), duration: Some(1), ..Default::default() };
+
+        let start = tokio::time::Instant::now();
+        let report = testing::shot(&job, &spec).await.unwrap();
+        assert!(start.elapsed() < Duration::from_millis(1800));
+        assert_eq!(report.evaluated.times, 20);
+        assert!(report.evaluated.assess().success());
+    }
+
+    #[tokio::test]
+    async fn test_open_model_with_stages() {
+        let stages = vec![
+            Stage { duration: Duration::from_millis(500), target: 40.0, from: None },
+            Stage { duration: Duration::from_millis(500), target: 40.0, from: None },
+        ];
+        let testcase = Testcase {
+            target: "/wait".to_string(),
+            profile: Profile {
+                request: ValueRequest { value: Some(json!(100)) },
+                stages: Some(stages),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let job = Job(vec![testing::suite("/wait", ["test"], vec![testcase])]);
+
+        let report = testing::shot(&job, &JobSpec::default()).await.unwrap();
+        let case = &report.suites[0].cases[0];
+        let times: Vec<_> = case.stages.iter().map(|s| s.evaluated.times).collect();
+        assert_eq!(times, vec![10, 20]);
+        assert_eq!(case.stages[1].metric.times(), 20);
+        assert_eq!(report.evaluated.times, 30);
+        assert!(report.evaluated.assess().success());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_closed_model_with_virtual_users() {
+        let testcase = Testcase {
+            target: "/wait".to_string(),
+            profile: Profile { request: ValueRequest { value: Some(json!(100)) }, ..Default::default() },
+            ..Default::default()
+        };
+        let mut suite = testing::suite("/wait", ["test"], vec![testcase.clone(), testcase]);
+        let think_time = Some(Duration::from_millis(100));
+        suite.suite.users = Some(VirtualUsers { count: 3, iterations: Some(2), think_time, ..Default::default() });
+        let job = Job(vec![suite]);
+
+        let start = tokio::time::Instant::now();
+        let report = testing::shot(&job, &JobSpec::default()).await.unwrap();
+        assert!((Duration::from_millis(500)..Duration::from_millis(900)).contains(&start.elapsed()));
+
+        let suite = &report.suites[0];
+        assert_eq!(suite.users.iter().map(|u| (u.iterations, u.evaluated.times)).collect::<Vec<_>>(), vec![(2, 4); 3]);
+        assert_eq!(suite.cases.iter().map(|c| c.evaluated.times).collect::<Vec<_>>(), vec![6, 6]);
+        assert!(report.evaluated.assess().success());
+    }
+}
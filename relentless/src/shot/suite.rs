@@ -1,24 +1,29 @@
-use std::fmt::Debug;
+use std::fmt::{Debug, Display};
 
 use futures::{StreamExt, TryStreamExt};
 use http::Uri;
-use semigroup::{CombineIterator, Lazy, Semigroup};
+use semigroup::{CombineIterator, Lazy, Monoid, Semigroup};
 use serde::{Deserialize, Serialize};
 use tower::{Layer, MakeService, Service};
 
 use crate::{
+    evaluator::evaluate::{MessageExt, Messages, Quorum},
     http_newtype_serde,
+    record::metric::MetricAgg,
     shot::{
         contract::{Contract, Evaluated, RequestSource, ResponseSink, ServiceError, SignContract},
         destinations::Destinations,
+        filter::Filter,
         hierarchy::Hierarchy,
         job::JobSpec,
         profile::Profile,
+        scheduler::VirtualUsers,
         testcase::{CaseReport, Testcase},
     },
+    template::Template,
 };
 
-#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
 #[serde(deny_unknown_fields, rename_all = "kebab-case")]
 pub struct SuiteCase<C, Q, P> {
     #[serde(flatten)]
@@ -28,14 +33,34 @@ pub struct SuiteCase<C, Q, P> {
     pub testcases: Vec<Testcase<Q, P>>,
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
 #[serde(deny_unknown_fields, rename_all = "kebab-case")]
 pub struct Suite<C, Q, P> {
     pub name: String,
     pub destinations: Destinations<http_newtype_serde::Uri>,
+    /// destination that each of the others is compared with, the first one of `destinations` by default
+    #[serde(default)]
+    pub baseline: Option<String>,
     #[serde(default)]
     pub profile: Profile<Q, P>,
     pub contract: Option<C>,
+    /// closed model, virtual users loop over testcases in order instead of running them concurrently
+    #[serde(default)]
+    pub users: Option<VirtualUsers>,
+    /// requests to every destination before the first testcase, such as resetting state
+    #[serde(default)]
+    pub setup: Vec<Testcase<Q, P>>,
+    /// requests to every destination after the last testcase
+    #[serde(default)]
+    pub teardown: Vec<Testcase<Q, P>>,
+    /// tags inherited by each testcase
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// run only suites or testcases marked as `only` in the job
+    #[serde(default)]
+    pub only: bool,
+    #[serde(default)]
+    pub skip: bool,
 }
 // impl<Q, P> Suite<Q, P> {
 //     pub async fn transport<'a, M, S, C>(
@@ -123,18 +148,32 @@ pub struct Suite<C, Q, P> {
 pub struct SuiteReport<'a, C, Q, P, M> {
     pub destinations: Lazy<Destinations<Uri>>,
     pub suite: &'a Suite<C, Q, P>,
+    pub setup: Vec<CaseReport<'a, Q, P, M>>,
     pub cases: Vec<CaseReport<'a, Q, P, M>>,
+    pub teardown: Vec<CaseReport<'a, Q, P, M>>,
+    pub users: Vec<UserReport>,
+    pub messages: Messages<M>,
+    pub evaluated: Evaluated,
+    /// metrics of requests to each destination, aggregated over testcases except setup and teardown
+    pub metrics: Destinations<MetricAgg>,
+}
+#[derive(Debug, Clone, PartialEq)]
+pub struct UserReport {
+    pub iterations: usize,
     pub evaluated: Evaluated,
 }
 impl<S, Q, P> SuiteCase<S, Q, P> {
+    /// Testcases skipped by `filter` are reported as skipped, and the suite without any testcase to run sends no request.
     #[tracing::instrument(name = "suite", skip(make_service))]
     pub async fn shot<M, T, C>(
         &self,
         make_service: M,
         job: &JobSpec,
+        filter: &Filter,
     ) -> crate::Result<SuiteReport<'_, S, Q, P, P::Message>>
     where
         M: Clone + MakeService<http::Uri, C::TransportReq, Service = T>,
+        M::MakeError: Display,
         T: Clone + Service<C::TransportReq, Response = C::TransportRes> + Send,
         S: Debug + SignContract<T, C> + Default,
         C: Contract<T, Sign = S, ReqSource = Q, ResSink = P> + Layer<T>,
@@ -142,28 +181,349 @@ impl<S, Q, P> SuiteCase<S, Q, P> {
         Q: Debug + Clone + Semigroup + RequestSource<C::Request>,
         P: Debug + Clone + Semigroup + ResponseSink<Result<C::Response, ServiceError<T, C>>>,
     {
-        let buffers = if Hierarchy::Suite.contains(&job.sequential) { 1 } else { self.testcases.len().max(1) };
-        let destinations = job.destinations(&self.suite.destinations).unwrap_or_else(|e| todo!("{e}"));
-        let uris = destinations.combine_rev_clone();
+        let testcases: Vec<_> = self.testcases.iter().filter(|t| !filter.skip(&self.suite, t)).collect();
+        let buffers = if Hierarchy::Suite.contains(&job.sequential) { 1 } else { testcases.len().max(1) };
+        if testcases.is_empty() && !self.testcases.is_empty() {
+            // resolved like a running suite, but errors are not reported because no request is sent
+            let destinations = self.destinations::<P::Message>(job).unwrap_or_else(|(destinations, _)| destinations);
+            let cases = self.testcases.iter().map(CaseReport::skipped).collect();
+            let (suite, setup, teardown, users) = (&self.suite, Vec::new(), Vec::new(), Vec::new());
+            let (messages, evaluated) = (Messages::new(), Evaluated::identity());
+            let metrics = Destinations::default();
+            return Ok(SuiteReport {
+                destinations,
+                suite,
+                setup,
+                cases,
+                teardown,
+                users,
+                messages,
+                evaluated,
+                metrics,
+            });
+        }
+        if job.dry_run {
+            return Ok(self.dry_run::<T, C>(job, filter).await);
+        }
+        let (destinations, services) = match self.services::<M, T, C>(make_service, job).await {
+            Ok(services) => services,
+            Err((destinations, message)) => return Ok(self.failed(destinations, message)),
+        };
+        let (uris, mut captured) = (self.uris(&destinations), Destinations::default());
+        let mut setup = Vec::new();
+        for t in &self.suite.setup {
+            setup.push(t.shot_once::<T, S, C>(&services, &uris, job, &self.suite, &mut captured).await);
+        }
+        for t in &testcases {
+            let profile = t.profile.clone().semigroup(self.suite.profile.clone()).with_captured(&captured);
+            let profile = profile.with_base_path(&job.base_path);
+            profile.warmup::<T, C>(&services, &uris, &t.target).await;
+        }
+        let (cases, users) = match &self.suite.users {
+            Some(users) => self.users_shot(users, &testcases, &services, &uris, job, &captured).await,
+            // testcases run in order to pass captured values to later ones
+            None if buffers == 1 || self.captures() => {
+                let mut cases = Vec::new();
+                for t in &testcases {
+                    cases.push(t.shot(&services, &uris, job, &self.suite, &mut captured).await?);
+                }
+                (cases, Vec::new())
+            }
+            None => {
+                let cases: Vec<_> = futures::stream::iter(&testcases)
+                    .map(|t| {
+                        let (services, uris, mut captured) = (&services, &uris, captured.clone());
+                        async move { t.shot(services, uris, job, &self.suite, &mut captured).await }
+                    })
+                    .buffered(buffers)
+                    .try_collect()
+                    .await?;
+                (cases, Vec::new())
+            }
+        };
+        let mut ran = cases.into_iter();
+        let cases: Vec<_> = self
+            .testcases
+            .iter()
+            .map(|t| match filter.skip(&self.suite, t) {
+                true => CaseReport::skipped(t),
+                false => ran.next().expect("reports of testcases that are not skipped are in order"),
+            })
+            .collect();
+        let mut teardown = Vec::new();
+        for t in &self.suite.teardown {
+            teardown.push(t.shot_once::<T, S, C>(&services, &uris, job, &self.suite, &mut captured).await);
+        }
+        let evaluated = setup.iter().chain(&cases).chain(&teardown).map(|c| c.evaluated.clone()).combine();
+        let mut metrics = Destinations::default();
+        cases.iter().for_each(|c| metrics.merge(c.metrics.clone()));
+        let (suite, messages) = (&self.suite, Messages::new());
+        Ok(SuiteReport { destinations, suite, setup, cases, teardown, users, messages, evaluated, metrics })
+    }
+
+    /// Render requests of the setup, testcases, and teardown in order, without making any service.
+    async fn dry_run<T, C>(&self, job: &JobSpec, filter: &Filter) -> SuiteReport<'_, S, Q, P, P::Message>
+    where
+        T: Service<C::TransportReq, Response = C::TransportRes>,
+        S: Debug,
+        C: Contract<T, Sign = S, ReqSource = Q, ResSink = P> + Layer<T>,
+        C::Service: Service<C::Request, Response = C::Response>,
+        Q: Debug + Clone + Semigroup + RequestSource<C::Request>,
+        P: Debug + Clone + Semigroup + ResponseSink<Result<C::Response, ServiceError<T, C>>>,
+    {
+        let destinations = match self.destinations(job) {
+            Ok(destinations) => destinations,
+            Err((destinations, message)) => return self.failed(destinations, message),
+        };
+        let (uris, mut captured) = (self.uris(&destinations), Destinations::default());
+        let (mut setup, mut cases, mut teardown) = (Vec::new(), Vec::new(), Vec::new());
+        for t in &self.suite.setup {
+            setup.push(t.dry_run::<T, S, C>(&uris, &self.suite, &mut captured).await);
+        }
+        for t in &self.testcases {
+            match filter.skip(&self.suite, t) {
+                true => cases.push(CaseReport::skipped(t)),
+                false => cases.push(t.dry_run::<T, S, C>(&uris, &self.suite, &mut captured).await),
+            }
+        }
+        for t in &self.suite.teardown {
+            teardown.push(t.dry_run::<T, S, C>(&uris, &self.suite, &mut captured).await);
+        }
+        let evaluated = setup.iter().chain(&cases).chain(&teardown).map(|c| c.evaluated.clone()).combine();
+        let (suite, messages, users, metrics) = (&self.suite, Messages::new(), Vec::new(), Destinations::default());
+        SuiteReport { destinations, suite, setup, cases, teardown, users, messages, evaluated, metrics }
+    }
+
+    /// Report of the suite that failed before any request, the message is reported as failure of the whole suite.
+    fn failed<M>(&self, destinations: Lazy<Destinations<Uri>>, message: M) -> SuiteReport<'_, S, Q, P, M> {
+        let mut messages = Messages::new();
+        messages.error(message);
+        let evaluated = Evaluated::new(&Err::<(), _>(()), self.suite.profile.allow);
+        let (suite, setup, cases, teardown, users) = (&self.suite, Vec::new(), Vec::new(), Vec::new(), Vec::new());
+        let metrics = Destinations::default();
+        SuiteReport { destinations, suite, setup, cases, teardown, users, messages, evaluated, metrics }
+    }
+
+    /// Destinations overwritten by the job, invalid ones are reported with the destinations of the config.
+    /// Profiles that cannot run with the destinations or `users` are also reported before any request.
+    #[allow(clippy::type_complexity)] // TODO
+    fn destinations<M: MessageExt>(
+        &self,
+        job: &JobSpec,
+    ) -> Result<Lazy<Destinations<Uri>>, (Lazy<Destinations<Uri>>, M)> {
+        let destinations = job.destinations(&self.suite.destinations).map_err(|e| {
+            let destinations: Destinations<_> =
+                self.suite.destinations.iter().map(|(d, u)| (d, u.clone().into())).collect();
+            (destinations.into(), M::custom(format!("invalid destination: {e}")))
+        })?;
+        if let Some(baseline) = self.suite.baseline.as_ref().filter(|b| !self.uris(&destinations).contains_key(*b)) {
+            let message = M::custom(format!("baseline `{baseline}` is not in destinations"));
+            return Err((destinations, message));
+        }
+        let n = self.uris(&destinations).len();
+        let testcases = self.suite.setup.iter().chain(&self.testcases).chain(&self.suite.teardown);
+        let quorums =
+            std::iter::once(&self.suite.profile).chain(testcases.map(|t| &t.profile)).filter_map(|p| p.quorum.as_ref());
+        if let Some(Quorum(quorum)) = quorums.into_iter().find(|Quorum(q)| !(1..=n).contains(q)) {
+            let message = M::custom(format!("quorum {quorum} should be between 1 and the number of destinations {n}"));
+            return Err((destinations, message));
+        }
+        let mut profiles = std::iter::once(&self.suite.profile).chain(self.testcases.iter().map(|t| &t.profile));
+        if self.suite.users.is_some() && profiles.any(|p| p.repeat.0.is_some() || p.stages.is_some()) {
+            let message = M::custom("`repeat` and `stages` are not available with `users`, use `iterations` instead");
+            return Err((destinations, message));
+        }
+        Ok(destinations)
+    }
+
+    /// Destinations in order of the config, the baseline comes first to be compared with the others.
+    fn uris(&self, destinations: &Lazy<Destinations<Uri>>) -> Destinations<Uri> {
+        destinations.combine_rev_clone().ordered(&self.suite.destinations, self.suite.baseline.as_deref())
+    }
+
+    /// Whether any testcase captures values from responses.
+    fn captures(&self) -> bool {
+        self.suite.profile.capture.is_some() || self.testcases.iter().any(|t| t.profile.capture.is_some())
+    }
+
+    /// Whether any testcase is marked as `only`, so that the others in the job are skipped.
+    pub fn focused(&self) -> bool {
+        self.suite.only || self.testcases.iter().any(|t| t.only)
+    }
+
+    /// Failure of destinations, connection, or signing contract is reported as failure of the whole suite.
+    #[allow(clippy::type_complexity)] // TODO
+    async fn services<M, T, C>(
+        &self,
+        make_service: M,
+        job: &JobSpec,
+    ) -> Result<(Lazy<Destinations<Uri>>, Destinations<C::Service>), (Lazy<Destinations<Uri>>, P::Message)>
+    where
+        M: Clone + MakeService<http::Uri, C::TransportReq, Service = T>,
+        M::MakeError: Display,
+        T: Clone + Service<C::TransportReq, Response = C::TransportRes> + Send,
+        S: Debug + SignContract<T, C> + Default,
+        C: Contract<T, Sign = S, ReqSource = Q, ResSink = P> + Layer<T>,
+        C::Service: Clone + Service<C::Request, Response = C::Response> + Send,
+        P: ResponseSink<Result<C::Response, ServiceError<T, C>>>,
+    {
+        let destinations = self.destinations(job)?;
         let mut services = Destinations::default();
-        for (d, dest) in uris.iter() {
-            let transport = make_service.clone().make_service(dest.clone()).await.unwrap_or_else(|_| todo!());
-            let contract = self
+        for (d, dest) in self.uris(&destinations).iter() {
+            let failed = |e: &dyn Display| P::Message::custom(format!("{d}: {e}"));
+            let transport = match make_service.clone().make_service(dest.clone()).await {
+                Ok(transport) => transport,
+                Err(e) => return Err((destinations, failed(&e))),
+            };
+            let contract = match self
                 .suite
                 .contract
                 .as_ref()
                 .unwrap_or(&Default::default())
                 .sign_contract(transport.clone(), dest, &job.base_path)
                 .await
-                .unwrap_or_else(|_| todo!());
+            {
+                Ok(contract) => contract,
+                Err(e) => return Err((destinations, failed(&e))),
+            };
             services.insert(d.to_string(), contract.layer(transport));
         }
-        let cases: Vec<_> = futures::stream::iter(&self.testcases)
-            .map(|t| t.shot(&services, &uris, job, &self.suite))
-            .buffered(buffers)
-            .try_collect()
-            .await?;
-        let evaluated = cases.iter().map(|c| c.evaluated.clone()).combine();
-        Ok(SuiteReport { destinations, suite: &self.suite, cases, evaluated })
+        Ok((destinations, services))
+    }
+
+    /// Each virtual user sends one request per testcase to each destination in order, and reports of each iteration
+    /// are merged, so in-flight requests are bounded by the number of users. `latency` and `regression` are assessed
+    /// with the merged metrics. Values captured in the setup are shared, but the ones captured by testcases are kept
+    /// in each virtual user.
+    #[allow(clippy::type_complexity)] // TODO
+    async fn users_shot<'a, T, C>(
+        &'a self,
+        users: &VirtualUsers,
+        testcases: &[&'a Testcase<Q, P>],
+        services: &Destinations<C::Service>,
+        destinations: &Destinations<http::Uri>,
+        job: &JobSpec,
+        captured: &Destinations<Template>,
+    ) -> (Vec<CaseReport<'a, Q, P, P::Message>>, Vec<UserReport>)
+    where
+        T: Clone + Service<C::TransportReq, Response = C::TransportRes> + Send,
+        S: Debug + SignContract<T, C> + Default,
+        C: Contract<T, Sign = S, ReqSource = Q, ResSink = P> + Layer<T>,
+        C::Service: Clone + Service<C::Request, Response = C::Response> + Send,
+        Q: Debug + Clone + Semigroup + RequestSource<C::Request>,
+        P: Debug + Clone + Semigroup + ResponseSink<Result<C::Response, ServiceError<T, C>>>,
+    {
+        let user = |_| async move {
+            let mut captured = captured.clone();
+            let mut iterations = Vec::new();
+            let mut stream = std::pin::pin!(users.iterations());
+            while stream.next().await.is_some() {
+                let mut cases = Vec::new();
+                for t in testcases {
+                    cases.push(t.shot_once::<T, S, C>(services, destinations, job, &self.suite, &mut captured).await);
+                }
+                iterations.push(cases);
+            }
+            iterations
+        };
+        let iterations: Vec<_> =
+            futures::stream::iter(0..users.count).map(user).buffer_unordered(users.count.max(1)).collect().await;
+
+        let (mut cases, mut reports): (Vec<CaseReport<_, _, _>>, _) = (Vec::new(), Vec::new());
+        for user in iterations {
+            let evaluated = user.iter().flatten().map(|c| c.evaluated.clone()).combine();
+            reports.push(UserReport { iterations: user.len(), evaluated });
+            for iteration in user {
+                if cases.is_empty() {
+                    cases = iteration;
+                } else {
+                    cases.iter_mut().zip(iteration).for_each(|(c, i)| c.merge(i));
+                }
+            }
+        }
+        for case in &mut cases {
+            let profile = case.case.profile.clone().semigroup(self.suite.profile.clone());
+            if let Some(latency) = &profile.latency {
+                case.evaluated.semigroup_assign(latency.assess(&mut case.messages, &case.metrics, profile.allow));
+            }
+            if let Some(regression) = &profile.regression {
+                case.evaluated.semigroup_assign(regression.assess(&mut case.messages, &case.metrics, profile.allow));
+            }
+        }
+        (cases, reports)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use crate::{
+        shot::capture::Capture,
+        testing::{self, TestingClient, ValueRequest},
+    };
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_compare_with_baseline() {
+        let template = |seed: &str| Template::from_iter([("seed".to_string(), seed.to_string())]);
+        let testcase = Testcase {
+            target: "/echo".to_string(),
+            profile: Profile {
+                request: ValueRequest { value: Some(json!("${seed}")) },
+                template: [("actual", template("1")), ("canary", template("2")), ("expect", template("1"))]
+                    .into_iter()
+                    .collect(),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let mut suite = testing::suite("baseline", ["actual", "canary", "expect"], vec![testcase]);
+        suite.suite.baseline = Some("expect".to_string());
+
+        let (spec, filter) = (JobSpec::default(), Filter::default());
+        let report = suite.shot::<_, _, TestingClient>(TestingClient, &spec, &filter).await.unwrap();
+        assert_eq!(report.destinations.combine_rev_clone().keys().collect::<Vec<_>>(), ["actual", "canary", "expect"]);
+        let case = &report.cases[0];
+        assert!(!case.evaluated.pass);
+        assert_eq!(case.messages.to_string(), "canary vs expect: not equal body\n");
+    }
+
+    #[tokio::test]
+    async fn test_baseline_not_in_destinations() {
+        let testcase = Testcase { target: "/echo".to_string(), ..Default::default() };
+        let mut suite = testing::suite("baseline", ["test"], vec![testcase]);
+        suite.suite.baseline = Some("missing".to_string());
+
+        let (spec, filter) = (JobSpec::default(), Filter::default());
+        let report = suite.shot::<_, _, TestingClient>(TestingClient, &spec, &filter).await.unwrap();
+        assert!(!report.evaluated.pass);
+        assert!(report.cases.is_empty());
+        assert_eq!(report.messages.to_string(), "baseline `missing` is not in destinations\n");
+    }
+
+    #[tokio::test]
+    async fn test_dry_run_does_not_send_requests() {
+        let capture = [("id".to_string(), Capture::Pointer("/id".to_string()))].into_iter().collect();
+        let testcases = vec![
+            Testcase {
+                target: "/fail".to_string(),
+                profile: Profile { capture: Some(capture), ..Default::default() },
+                ..Default::default()
+            },
+            Testcase {
+                target: "/echo".to_string(),
+                profile: Profile { request: ValueRequest { value: Some(json!("${id}")) }, ..Default::default() },
+                ..Default::default()
+            },
+        ];
+        let suite = testing::suite("dry run", ["test"], testcases);
+
+        let (spec, filter) = (JobSpec { dry_run: true, ..Default::default() }, Filter::default());
+        let report = suite.shot::<_, _, TestingClient>(TestingClient, &spec, &filter).await.unwrap();
+        let requests: Vec<_> = report.cases.iter().map(|c| c.requests["test"].clone()).collect();
+        assert_eq!(requests, vec!["/fail null", r#"/echo ":id""#]);
+        assert!(report.evaluated.pass);
     }
 }
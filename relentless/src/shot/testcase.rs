@@ -1,28 +1,39 @@
 use std::fmt::Debug;
 
 use futures::StreamExt;
-use semigroup::{Semigroup, TryCombineStream};
+use semigroup::{Monoid, Semigroup};
 use serde::{Deserialize, Serialize};
 use tower::{Layer, Service};
 
 use crate::{
-    evaluator::evaluate::Messages,
+    evaluator::evaluate::{MessageExt, Messages},
+    record::metric::MetricAgg,
     shot::{
+        capture,
         contract::{Contract, Evaluated, RequestSource, ResponseSink, ServiceError, SignContract},
         destinations::Destinations,
         hierarchy::Hierarchy,
         job::JobSpec,
         profile::Profile,
+        scheduler::Stage,
         suite::Suite,
     },
+    template::Template,
 };
 
-#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
 #[serde(deny_unknown_fields, rename_all = "kebab-case")]
 pub struct Testcase<Q, P> {
     #[serde(default)]
     pub description: Option<String>,
     pub target: String,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// run only testcases marked as `only` in the job
+    #[serde(default)]
+    pub only: bool,
+    #[serde(default)]
+    pub skip: bool,
 
     #[serde(default)]
     pub profile: Profile<Q, P>,
@@ -33,9 +44,111 @@ pub struct CaseReport<'a, Q, P, M> {
     pub case: &'a Testcase<Q, P>,
     pub evaluated: Evaluated,
     pub messages: Messages<M>,
+    pub stages: Vec<StageReport>,
+    /// metrics of requests to each destination
+    pub metrics: Destinations<MetricAgg>,
+    /// skipped by filters of the job, so no request was sent
+    pub skipped: bool,
+    /// rendered requests of each destination by `--dry-run`
+    pub requests: Destinations<String>,
+}
+
+impl<'a, Q, P, M> CaseReport<'a, Q, P, M> {
+    pub fn skipped(case: &'a Testcase<Q, P>) -> Self {
+        let (evaluated, messages) = (Evaluated::identity(), Messages::identity());
+        let (stages, metrics, requests) = (Vec::new(), Destinations::default(), Destinations::default());
+        Self { case, evaluated, messages, stages, metrics, skipped: true, requests }
+    }
+
+    /// Merge reports of the same testcase, such as the ones of each iteration of virtual users.
+    pub fn merge(&mut self, other: Self) {
+        self.evaluated.semigroup_assign(other.evaluated);
+        self.messages.semigroup_assign(other.messages);
+        self.metrics.merge(other.metrics);
+        self.stages.iter_mut().zip(other.stages).for_each(|(s, o)| {
+            s.evaluated.semigroup_assign(o.evaluated);
+            s.metric.semigroup_assign(o.metric);
+        });
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct StageReport {
+    pub stage: Stage,
+    pub evaluated: Evaluated,
+    /// metrics of requests to all destinations in the stage
+    pub metric: MetricAgg,
 }
 
 impl<Q, P> Testcase<Q, P> {
+    /// Shot once regardless of `repeat` and load of the job, such as setup and teardown of a suite.
+    #[tracing::instrument(name = "hook", skip(services))]
+    pub async fn shot_once<T, S, C>(
+        &self,
+        services: &Destinations<C::Service>,
+        destinations: &Destinations<http::Uri>,
+        job: &JobSpec,
+        suite: &Suite<S, Q, P>,
+        captured: &mut Destinations<Template>,
+    ) -> CaseReport<'_, Q, P, P::Message>
+    where
+        T: Clone + Service<C::TransportReq, Response = C::TransportRes> + Send,
+        S: Debug,
+        C: Contract<T, Sign = S, ReqSource = Q, ResSink = P> + Layer<T>,
+        C::Service: Clone + Service<C::Request, Response = C::Response>,
+        Q: Debug + Clone + Semigroup + RequestSource<C::Request>,
+        P: Debug + Clone + Semigroup + ResponseSink<Result<C::Response, ServiceError<T, C>>>,
+    {
+        let profile = self
+            .profile
+            .clone()
+            .semigroup(suite.profile.clone())
+            .with_captured(captured)
+            .with_base_path(&job.base_path);
+        let (evaluated, messages, capture, metrics) = profile.shot::<T, C>(services, destinations, &self.target).await;
+        capture::merge(captured, capture);
+        let (stages, requests) = (Vec::new(), Destinations::default());
+        CaseReport { case: self, evaluated, messages, stages, metrics, skipped: false, requests }
+    }
+
+    /// Render requests to each destination without sending them, values to be captured are left as placeholders.
+    #[tracing::instrument(name = "dry_run")]
+    pub async fn dry_run<T, S, C>(
+        &self,
+        destinations: &Destinations<http::Uri>,
+        suite: &Suite<S, Q, P>,
+        captured: &mut Destinations<Template>,
+    ) -> CaseReport<'_, Q, P, P::Message>
+    where
+        T: Service<C::TransportReq, Response = C::TransportRes>,
+        S: Debug,
+        C: Contract<T, Sign = S, ReqSource = Q, ResSink = P> + Layer<T>,
+        C::Service: Service<C::Request, Response = C::Response>,
+        Q: Debug + Clone + Semigroup + RequestSource<C::Request>,
+        P: Debug + Clone + Semigroup + ResponseSink<Result<C::Response, ServiceError<T, C>>>,
+    {
+        let profile = self.profile.clone().semigroup(suite.profile.clone()).with_captured(captured);
+        let (mut messages, mut requests) = (Messages::new(), Destinations::default());
+        for (name, destination) in destinations {
+            let template = profile.template.get(name).cloned().unwrap_or_default();
+            match profile.request.dry_run(destination, &self.target, &template).await {
+                Ok(request) => {
+                    requests.insert(name.clone(), request);
+                }
+                Err(e) => {
+                    messages.error(P::Message::custom(format!("{name}: {e}")));
+                }
+            }
+            let placeholders = profile.capture.iter().flatten().map(|(var, _)| (var.clone(), format!(":{var}")));
+            captured.entry(name.clone()).or_default().extend(placeholders);
+        }
+        let rendered = if messages.is_empty() { Ok(()) } else { Err(()) };
+        let evaluated = Evaluated::new(&rendered, profile.allow);
+        let (stages, metrics) = (Vec::new(), Destinations::default());
+        CaseReport { case: self, evaluated, messages, stages, metrics, skipped: false, requests }
+    }
+
+    /// Variables in `captured` are available in the template, and values captured by this testcase are added to it.
     #[tracing::instrument(name = "testcase", skip(services))]
     pub async fn shot<T, S, C>(
         &self,
@@ -43,6 +156,7 @@ impl<Q, P> Testcase<Q, P> {
         destinations: &Destinations<http::Uri>,
         job: &JobSpec,
         suite: &Suite<S, Q, P>,
+        captured: &mut Destinations<Template>,
     ) -> crate::Result<CaseReport<'_, Q, P, P::Message>>
     where
         T: Clone + Service<C::TransportReq, Response = C::TransportRes> + Send,
@@ -52,15 +166,62 @@ impl<Q, P> Testcase<Q, P> {
         Q: Debug + Clone + Semigroup + RequestSource<C::Request>,
         P: Debug + Clone + Semigroup + ResponseSink<Result<C::Response, ServiceError<T, C>>>,
     {
-        let profile = &self.profile.clone().semigroup(suite.profile.clone());
-        let buffers = if Hierarchy::Testcase.contains(&job.sequential) { 1 } else { profile.repeat.times().max(1) };
+        let profile = &self
+            .profile
+            .clone()
+            .semigroup(suite.profile.clone())
+            .with_captured(captured)
+            .with_base_path(&job.base_path);
+        let scheduler = profile.scheduler(job);
+        let shot = |stage| async move {
+            let (evaluated, messages, capture, metrics) =
+                profile.shot::<T, C>(services, destinations, &self.target).await;
+            (stage, evaluated, messages, metrics, capture)
+        };
+        let shots = match &scheduler {
+            // open model: requests are fired on schedule, so in-flight requests are not bounded
+            Some(scheduler) => {
+                scheduler.ticks().map(|stage| shot(Some(stage))).buffer_unordered(usize::MAX).left_stream()
+            }
+            None => {
+                let buffers =
+                    if Hierarchy::Testcase.contains(&job.sequential) { 1 } else { profile.repeat.times().max(1) };
+                futures::stream::iter(profile.repeat.range())
+                    .map(|_| shot(None))
+                    .buffer_unordered(buffers)
+                    .right_stream()
+            }
+        };
 
-        let (evaluated, messages) = futures::stream::iter(profile.repeat.range())
-            .map(|_| async { profile.shot::<T, C>(services, destinations, &self.target).await })
-            .buffer_unordered(buffers)
-            .try_combine_monoid()
-            .await
-            .unwrap_or_else(|_| todo!());
-        Ok(CaseReport { case: self, evaluated, messages })
+        let stages: Vec<_> = profile
+            .stages
+            .iter()
+            .flatten()
+            .map(|stage| StageReport {
+                stage: stage.clone(),
+                evaluated: Evaluated::identity(),
+                metric: MetricAgg::identity(),
+            })
+            .collect();
+        let init = (Evaluated::identity(), Messages::identity(), stages, Destinations::default());
+        let (mut evaluated, mut messages, stages, metrics) = shots
+            .fold(init, |(evaluated, messages, mut stages, mut metrics), (stage, e, m, ms, capture)| {
+                if let Some(report) = stage.and_then(|s| stages.get_mut(s)) {
+                    report.evaluated.semigroup_assign(e.clone());
+                    ms.values().for_each(|metric| report.metric.semigroup_assign(metric.clone()));
+                }
+                metrics.merge(ms);
+                capture::merge(captured, capture);
+                async move { (evaluated.semigroup(e), messages.semigroup(m), stages, metrics) }
+            })
+            .await;
+        if let Some(latency) = &profile.latency {
+            evaluated.semigroup_assign(latency.assess(&mut messages, &metrics, profile.allow));
+        }
+        if let Some(regression) = &profile.regression {
+            evaluated.semigroup_assign(regression.assess(&mut messages, &metrics, profile.allow));
+        }
+        let requests = Destinations::default();
+        Ok(CaseReport { case: self, evaluated, messages, stages, metrics, skipped: false, requests })
     }
 }
@@ -26,6 +26,10 @@ impl Template {
         Default::default()
     }
 
+    pub fn insert<K: Into<String>, V: Into<String>>(&mut self, var: K, value: V) {
+        self.vars.insert(var.into(), value.into());
+    }
+
     pub fn render(&self, input: &str) -> crate::Result<String> {
         let variables = Variable::split(input)?;
         let assigned = variables.iter().map(|v| v.assign(self)).collect::<Result<Vec<_>, _>>()?;
@@ -51,6 +55,24 @@ impl Template {
     }
 }
 
+impl FromIterator<(String, String)> for Template {
+    fn from_iter<I: IntoIterator<Item = (String, String)>>(iter: I) -> Self {
+        Self { vars: iter.into_iter().collect() }
+    }
+}
+impl Extend<(String, String)> for Template {
+    fn extend<I: IntoIterator<Item = (String, String)>>(&mut self, iter: I) {
+        self.vars.extend(iter)
+    }
+}
+impl IntoIterator for Template {
+    type Item = (String, String);
+    type IntoIter = std::collections::hash_map::IntoIter<String, String>;
+    fn into_iter(self) -> Self::IntoIter {
+        self.vars.into_iter()
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum Variable {
     Literal(String),
@@ -15,14 +15,20 @@ use tower::{layer::util::Identity, Layer, Service};
 use crate::{
     error::EvaluateError,
     evaluator::{
-        evaluate::{Evaluator, Failure, Messages},
+        evaluate::{Evaluator, Failure, Messages, Quorum},
         expect::ExpectEvaluator,
     },
+    http_newtype_serde,
+    record::metric::{BodySize, BodySizeRecorder},
     shot::{
+        capture::Captures,
         contract::{Contract, RequestSource, ResponseSink, SignContract},
         destinations::Destinations,
-        job::BasePath,
+        job::{BasePath, Job, JobReport, JobSpec},
+        suite::{Suite, SuiteCase},
+        testcase::Testcase,
     },
+    template::Template,
 };
 
 #[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize, Semigroup)]
@@ -33,14 +39,14 @@ pub struct ValueRequest {
 }
 impl RequestSource<(String, Value)> for ValueRequest {
     type Error = Infallible;
-    async fn produce(
-        &self,
-        _: &http::Uri,
-        target: &str,
-        template: &crate::template::Template,
-    ) -> Result<(String, Value), Self::Error> {
+    async fn produce(&self, _: &http::Uri, target: &str, template: &Template) -> Result<(String, Value), Self::Error> {
         let target = template.render(target).unwrap_or_else(|_| target.to_string());
-        Ok((target, self.value.clone().unwrap_or_default()))
+        let value = self.value.as_ref().map(|v| template.render_json_recursive(v).unwrap_or_else(|_| v.clone()));
+        Ok((target, value.unwrap_or_default()))
+    }
+    async fn dry_run(&self, destination: &http::Uri, target: &str, template: &Template) -> Result<String, Self::Error> {
+        let (target, value) = self.produce(destination, target, template).await?;
+        Ok(format!("{target} {value}"))
     }
 }
 
@@ -64,6 +70,8 @@ impl<E: Display + Send> ResponseSink<Result<Value, E>> for ValueResponse {
     async fn consume(
         &self,
         msg: &mut Messages<Self::Message>,
+        captures: &mut Captures,
+        quorum: Option<&Quorum>,
         res: Destinations<Result<Value, E>>,
     ) -> Result<(), Failure> {
         let buffers = res.len().max(1);
@@ -73,7 +81,8 @@ impl<E: Display + Send> ResponseSink<Result<Value, E>> for ValueResponse {
             .try_collect()
             .await
             .map_err(|e| msg.error(e))?;
-        self.value.as_ref().unwrap_or(&Default::default()).evaluate(msg, collected)
+        collected.iter().for_each(|(d, v)| captures.capture_json(msg, d, v));
+        self.value.as_ref().unwrap_or(&Default::default()).evaluate(msg, quorum, collected)
     }
 }
 impl Evaluator<Value> for ValueResponseInner {
@@ -111,6 +120,12 @@ impl Service<(String, Value)> for TestingClient {
                     tokio::time::sleep(std::time::Duration::from_millis(wait)).await;
                     Ok(json!(wait))
                 }
+                "/delay" => {
+                    // like `/wait`, but also rendered templates, and the response does not depend on the delay
+                    let delay = value.as_u64().or_else(|| value.as_str()?.parse().ok()).unwrap_or(100);
+                    tokio::time::sleep(std::time::Duration::from_millis(delay)).await;
+                    Ok(Value::Null)
+                }
                 _ => Err(crate::Error::custom("unimplemented")),
             }
         })
@@ -145,6 +160,13 @@ impl Contract<Self> for TestingClient {
     type ResSink = ValueResponse;
 
     type SignError = Infallible;
+
+    fn request_size((_, value): &Self::Request) -> Option<u64> {
+        Some(value.to_string().len() as u64)
+    }
+    fn body_size(response: &mut Self::Response) -> BodySizeRecorder {
+        BodySize { response: Some(response.to_string().len() as u64), ..Default::default() }.into()
+    }
 }
 impl SignContract<Self, Self> for TestingClient {
     type Error = Infallible;
@@ -153,16 +175,31 @@ impl SignContract<Self, Self> for TestingClient {
     }
 }
 
+/// Suite of `testcases` requesting each of `destinations` by [`TestingClient`], to test features through a whole job.
+pub fn suite<'a>(
+    name: &str,
+    destinations: impl IntoIterator<Item = &'a str>,
+    testcases: Vec<Testcase<ValueRequest, ValueResponse>>,
+) -> SuiteCase<TestingClient, ValueRequest, ValueResponse> {
+    let uri = || http_newtype_serde::Uri("http://localhost:8080".parse().unwrap());
+    let destinations = destinations.into_iter().map(|d| (d, uri())).collect();
+    let suite = Suite { name: name.to_string(), contract: Some(TestingClient), destinations, ..Default::default() };
+    SuiteCase { suite, testcases }
+}
+
+/// Run the job by [`TestingClient`].
+pub async fn shot<'a>(
+    job: &'a Job<TestingClient, ValueRequest, ValueResponse>,
+    spec: &JobSpec,
+) -> crate::Result<JobReport<'a, TestingClient, ValueRequest, ValueResponse, EvaluateError>> {
+    job.shot::<TestingClient, TestingClient, TestingClient>(TestingClient, spec).await
+}
+
 #[cfg(test)]
 mod tests {
     use crate::{
         report::{ReportFormat, Reporter},
-        shot::{
-            job::{Job, JobSpec},
-            profile::Profile,
-            suite::{Suite, SuiteCase},
-            testcase::Testcase,
-        },
+        shot::profile::Profile,
     };
 
     use super::*;
@@ -170,53 +207,27 @@ mod tests {
     #[tokio::test]
     async fn test_compile_with_echo_service() {
         let (job, spec) = (Job(Vec::new()), JobSpec::default());
-        let make = TestingClient;
 
-        let report = job.shot::<TestingClient, TestingClient, TestingClient>(make, &spec).await.unwrap();
+        let report = shot(&job, &spec).await.unwrap();
         spec.report(&report).unwrap();
         assert!(report.evaluated.assess().success());
     }
 
     #[tokio::test]
     async fn test_with_echo_service() {
-        let suites = vec![SuiteCase {
-            suite: Suite {
-                name: "/echo".to_string(),
-                contract: Some(TestingClient),
-                destinations: vec![("test", crate::http_newtype_serde::Uri("http://localhost:8080".parse().unwrap()))]
-                    .into_iter()
-                    .collect(),
+        let testcase = |value: Value| Testcase {
+            target: "/echo".to_string(),
+            profile: Profile {
+                request: ValueRequest { value: Some(value.clone()) },
+                response: ValueResponse { value: Some(ValueResponseInner::Expect(ExpectEvaluator::new(value))) },
                 ..Default::default()
             },
-            testcases: vec![
-                Testcase {
-                    target: "/echo".to_string(),
-                    profile: Profile {
-                        request: ValueRequest { value: Some(json!("hello")) },
-                        response: ValueResponse {
-                            value: Some(ValueResponseInner::Expect(ExpectEvaluator::new(json!("hello")))),
-                        },
-                        ..Default::default()
-                    },
-                    ..Default::default()
-                },
-                Testcase {
-                    target: "/echo".to_string(),
-                    profile: Profile {
-                        request: ValueRequest { value: Some(json!("value")) },
-                        response: ValueResponse {
-                            value: Some(ValueResponseInner::Expect(ExpectEvaluator::new(json!("value")))),
-                        },
-                        ..Default::default()
-                    },
-                    ..Default::default()
-                },
-            ],
-        }];
-        let (job, spec) = (Job(suites), JobSpec { report_format: ReportFormat::NullDevice, ..Default::default() });
-        let make = TestingClient;
-
-        let report = job.shot::<TestingClient, TestingClient, TestingClient>(make, &spec).await.unwrap();
+            ..Default::default()
+        };
+        let job = Job(vec![suite("/echo", ["test"], vec![testcase(json!("hello")), testcase(json!("value"))])]);
+        let spec = JobSpec { report_format: ReportFormat::NullDevice, ..Default::default() };
+
+        let report = shot(&job, &spec).await.unwrap();
         spec.report(&report).unwrap();
         assert!(report.evaluated.assess().success());
     }